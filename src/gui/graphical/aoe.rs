@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+//Following the cursor does not need to happen every frame
+const AOE_RECENTER_INTERVAL: Duration = Duration::from_millis(50);
+
+//Tiles are kept as offsets from the center so the pattern can follow the cursor
+pub struct AoePreview {
+    pub center: (u16, u16),
+    pub offsets: Vec<(i32, i32)>,
+    last_recenter: Instant,
+}
+
+impl AoePreview {
+    pub fn new(center: (u16, u16), tiles: &Vec<(u16, u16)>) -> Self {
+        AoePreview {
+            center,
+            offsets: tiles.iter()
+                .map(|t| (t.0 as i32 - center.0 as i32, t.1 as i32 - center.1 as i32))
+                .collect(),
+            last_recenter: Instant::now(),
+        }
+    }
+
+    pub fn recenter(&mut self, center: (u16, u16)) {
+        if center == self.center || self.last_recenter.elapsed() < AOE_RECENTER_INTERVAL {
+            return;
+        }
+        self.center = center;
+        self.last_recenter = Instant::now();
+    }
+
+    fn radius(&self) -> f32 {
+        self.offsets.iter()
+            .map(|(x, y)| ((x * x + y * y) as f32).sqrt())
+            .fold(0., f32::max)
+    }
+
+    //Strength goes from 1 on the center to a minimum on the farthest tile
+    pub fn tiles(&self, board_size: (u16, u16)) -> Vec<((u16, u16), f32)> {
+        let radius = self.radius();
+        self.offsets.iter()
+            .map(|(x, y)| ((self.center.0 as i32 + x, self.center.1 as i32 + y), ((x * x + y * y) as f32).sqrt()))
+            .filter(|((x, y), _)| *x >= 0 && *y >= 0 && *x < board_size.0 as i32 && *y < board_size.1 as i32)
+            .map(|((x, y), distance)| {
+                let strength = if radius > 0. { 1. - 0.7 * distance / radius } else { 1. };
+                ((x as u16, y as u16), strength)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AoePreview;
+
+    #[test]
+    fn strength_fades_away_from_the_center_and_tiles_stay_on_the_board() {
+        let mut preview = AoePreview::new((1, 0), &vec![(0, 0), (1, 0), (2, 0)]);
+        preview.center = (0, 0);
+
+        let tiles = preview.tiles((4, 4));
+
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[0], ((0, 0), 1.));
+        assert_eq!(tiles[1].0, (1, 0));
+        assert!((tiles[1].1 - 0.3).abs() < 0.001);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,55 @@
+use ggez::graphics::DrawParam;
+
+//Background batches cover square chunks of the board so the ones out of view can be skipped
+pub const BACKGROUND_CHUNK_SIZE: i32 = 16;
+
+pub fn chunk_of(tile: (i32, i32)) -> (i32, i32) {
+    (tile.0.div_euclid(BACKGROUND_CHUNK_SIZE), tile.1.div_euclid(BACKGROUND_CHUNK_SIZE))
+}
+
+//Only consecutive draws sharing a texture are merged, so the draw order is kept across textures
+pub fn texture_runs(draws: impl IntoIterator<Item = (u16, DrawParam)>) -> Vec<(u16, Vec<DrawParam>)> {
+    let mut runs: Vec<(u16, Vec<DrawParam>)> = vec![];
+    for (texture_id, param) in draws {
+        match runs.last_mut() {
+            Some((last_id, params)) if *last_id == texture_id => params.push(param),
+            _ => runs.push((texture_id, vec![param]))
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use ggez::graphics::DrawParam;
+    use super::{chunk_of, texture_runs};
+
+    #[test]
+    fn consecutive_draws_of_a_texture_share_a_run() {
+        let draws = vec![(1, DrawParam::new()), (1, DrawParam::new()), (2, DrawParam::new()), (1, DrawParam::new())];
+
+        let runs = texture_runs(draws).into_iter()
+            .map(|(texture_id, params)| (texture_id, params.len()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(runs, vec![(1, 2), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn negative_tiles_fall_in_the_chunk_before_zero() {
+        assert_eq!(chunk_of((0, 15)), (0, 0));
+        assert_eq!(chunk_of((16, -1)), (1, -1));
+    }
+
+    //2000 background sprites were 2000 draw calls, one per sprite
+    #[test]
+    fn a_2000_sprites_background_needs_a_few_draw_calls() {
+        let batches = (0..2000)
+            .map(|i| ((i % 50, i / 50), (i % 4) as u16))
+            .map(|(tile, texture_id)| (chunk_of(tile), texture_id))
+            .collect::<BTreeSet<_>>();
+
+        assert_eq!(batches.len(), 4 * 3 * 4);
+    }
+}
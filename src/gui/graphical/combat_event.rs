@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum CombatEventKind {
+    DAMAGE,
+    HEAL,
+    MISS,
+    CRITICAL,
+    STATUS
+}
+
+//Sent by the gameplay on the combat_event topic, target is the tile of the affected pawn
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CombatEvent {
+    pub kind: CombatEventKind,
+    pub target: (u16, u16),
+    pub value: u8,
+}
@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawMode, Mesh};
+
+const PING_DURATION: Duration = Duration::from_millis(1200);
+const PING_MAX_RADIUS: f32 = 24.;
+//Pixels per second, a longer shot takes longer to land
+const PROJECTILE_SPEED: f32 = 400.;
+const PROJECTILE_RADIUS: f32 = 3.;
+
+//Ring growing on a tile to draw the attention of the player, sent by the gameplay on the ping topic
+pub struct Ping {
+    pub position: Vec2,
+    pub start: Instant,
+}
+
+impl Ping {
+    //Position is the world position of the center of the tile
+    pub fn new(position: Vec2) -> Self {
+        Ping {
+            position,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.start.elapsed() >= PING_DURATION
+    }
+
+    pub fn radius_at(elapsed: Duration) -> f32 {
+        (elapsed.as_secs_f32() / PING_DURATION.as_secs_f32()).min(1.) * PING_MAX_RADIUS
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, color: Color) -> GameResult<()> {
+        let progress = (self.start.elapsed().as_secs_f32() / PING_DURATION.as_secs_f32()).min(1.);
+        let ring = Mesh::new_circle(ctx,
+                                    DrawMode::stroke(2.),
+                                    self.position,
+                                    Self::radius_at(self.start.elapsed()).max(1.),
+                                    0.5,
+                                    Color::new(color.r, color.g, color.b, color.a * (1. - progress)))?;
+        canvas.draw(&ring, Vec2::new(0., 0.));
+        Ok(())
+    }
+}
+
+//Travels in a straight line from the attacker to its target, sent by the gameplay on the projectile topic
+pub struct Projectile {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub start: Instant,
+}
+
+impl Projectile {
+    //Both ends are world positions of tile centers
+    pub fn new(from: Vec2, to: Vec2) -> Self {
+        Projectile {
+            from,
+            to,
+            start: Instant::now(),
+        }
+    }
+
+    fn flight_time(&self) -> Duration {
+        Duration::from_secs_f32(self.from.distance(self.to) / PROJECTILE_SPEED)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.start.elapsed() >= self.flight_time()
+    }
+
+    pub fn position_at(&self, elapsed: Duration) -> Vec2 {
+        let flight_time = self.flight_time().as_secs_f32();
+        if flight_time <= 0. {
+            return self.to;
+        }
+        self.from.lerp(self.to, (elapsed.as_secs_f32() / flight_time).min(1.))
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, color: Color) -> GameResult<()> {
+        let shot = Mesh::new_circle(ctx,
+                                    DrawMode::fill(),
+                                    self.position_at(self.start.elapsed()),
+                                    PROJECTILE_RADIUS,
+                                    0.5,
+                                    color)?;
+        canvas.draw(&shot, Vec2::new(0., 0.));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ggez::glam::Vec2;
+    use super::{Ping, PING_DURATION, PING_MAX_RADIUS, Projectile};
+
+    #[test]
+    fn ping_grows_until_it_expires() {
+        assert_eq!(Ping::radius_at(PING_DURATION / 2), PING_MAX_RADIUS / 2.);
+        assert_eq!(Ping::radius_at(PING_DURATION * 2), PING_MAX_RADIUS);
+
+        let ping = Ping { position: Vec2::ZERO, start: Instant::now() - PING_DURATION };
+        assert!(ping.is_expired());
+    }
+
+    #[test]
+    fn projectile_moves_at_constant_speed_and_stops_on_its_target() {
+        let projectile = Projectile::new(Vec2::ZERO, Vec2::new(400., 0.));
+
+        assert_eq!(projectile.position_at(Duration::from_millis(500)), Vec2::new(200., 0.));
+        assert_eq!(projectile.position_at(Duration::from_secs(3)), Vec2::new(400., 0.));
+        assert!(!projectile.is_expired());
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Text};
+use crate::gui::graphical::combat_event::{CombatEvent, CombatEventKind};
+use crate::gui::graphical::text::{draw_text, TextShadow};
+
+const FLOATING_TEXT_DURATION: Duration = Duration::from_millis(900);
+//Pixels per second the text goes up while fading
+const FLOATING_TEXT_SPEED: f32 = 30.;
+
+pub struct FloatingText {
+    pub text: String,
+    pub position: Vec2,
+    pub color: Color,
+    pub scale: f32,
+    pub start: Instant,
+}
+
+impl FloatingText {
+    //Position is the world position of the top left corner of the target tile
+    pub fn from_combat_event(event: &CombatEvent, position: Vec2) -> Self {
+        let (text, color, scale) = match event.kind {
+            CombatEventKind::DAMAGE => (format!("-{}", event.value), Color::RED, 1.),
+            CombatEventKind::HEAL => (format!("+{}", event.value), Color::GREEN, 1.),
+            CombatEventKind::MISS => ("Miss".to_string(), Color::new(0.6, 0.6, 0.6, 1.), 1.),
+            CombatEventKind::CRITICAL => (format!("-{}!", event.value), Color::RED, 1.6),
+            CombatEventKind::STATUS => (format!("{}", event.value), Color::YELLOW, 0.8),
+        };
+
+        FloatingText {
+            text,
+            position,
+            color,
+            scale,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.start.elapsed() >= FLOATING_TEXT_DURATION
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, shadow: Option<TextShadow>) {
+        let progress = (self.start.elapsed().as_secs_f32() / FLOATING_TEXT_DURATION.as_secs_f32()).min(1.);
+        let position = self.position - Vec2::new(0., FLOATING_TEXT_SPEED * self.start.elapsed().as_secs_f32());
+        //The shadow fades along with the text
+        let shadow = shadow.map(|s| TextShadow {
+            offset: s.offset,
+            color: Color::new(s.color.r, s.color.g, s.color.b, s.color.a * (1. - progress)),
+        });
+
+        draw_text(canvas,
+                  &Text::new(self.text.as_str()),
+                  position,
+                  DrawParam::new()
+                      .scale(Vec2::new(self.scale, self.scale))
+                      .color(Color::new(self.color.r, self.color.g, self.color.b, 1. - progress)),
+                  shadow);
+    }
+}
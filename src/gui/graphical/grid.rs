@@ -0,0 +1,129 @@
+use ggez::glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+//World coordinate of the first square grid line at or before the given offset
+pub fn first_grid_line(offset: f32, tile_size: f32) -> f32 {
+    if tile_size <= 0. {
+        return offset;
+    }
+    (offset / tile_size).floor() * tile_size
+}
+
+//Hex rows (pointy) or columns (flat) overlap by a quarter of a tile, odd ones are shifted by half a tile
+const HEX_OVERLAP: f32 = 0.75;
+
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum GridKind {
+    #[default]
+    SQUARE,
+    HEX_POINTY,
+    HEX_FLAT
+}
+
+impl GridKind {
+    //Top left corner of the box the tile sprite is drawn in
+    pub fn tile_to_world(&self, tile: (i32, i32), tile_size: f32) -> Vec2 {
+        let (x, y) = (tile.0 as f32, tile.1 as f32);
+        match self {
+            GridKind::SQUARE => Vec2::new(x, y) * tile_size,
+            GridKind::HEX_POINTY => Vec2::new(x * tile_size + if tile.1 % 2 != 0 { tile_size / 2. } else { 0. },
+                                              y * tile_size * HEX_OVERLAP),
+            GridKind::HEX_FLAT => Vec2::new(x * tile_size * HEX_OVERLAP,
+                                            y * tile_size + if tile.0 % 2 != 0 { tile_size / 2. } else { 0. }),
+        }
+    }
+
+    pub fn tile_center(&self, tile: (i32, i32), tile_size: f32) -> Vec2 {
+        self.tile_to_world(tile, tile_size) + Vec2::splat(tile_size / 2.)
+    }
+
+    //For hexes the closest center wins, which follows the hex edges and not the square bounds
+    pub fn world_to_tile(&self, world: Vec2, tile_size: f32) -> (i32, i32) {
+        let estimate = match self {
+            GridKind::SQUARE => return ((world.x / tile_size).floor() as i32, (world.y / tile_size).floor() as i32),
+            GridKind::HEX_POINTY => ((world.x / tile_size).floor() as i32, (world.y / (tile_size * HEX_OVERLAP)).floor() as i32),
+            GridKind::HEX_FLAT => ((world.x / (tile_size * HEX_OVERLAP)).floor() as i32, (world.y / tile_size).floor() as i32),
+        };
+
+        let mut closest = estimate;
+        let mut closest_distance = f32::MAX;
+        for x in estimate.0 - 1..=estimate.0 + 1 {
+            for y in estimate.1 - 1..=estimate.1 + 1 {
+                let distance = self.tile_center((x, y), tile_size).distance_squared(world);
+                if distance < closest_distance {
+                    closest = (x, y);
+                    closest_distance = distance;
+                }
+            }
+        }
+        closest
+    }
+
+    //Outline of the tile, hexes are stretched to fill the tile box so neighbours share their edges
+    pub fn tile_corners(&self, tile: (i32, i32), tile_size: f32) -> Vec<Vec2> {
+        let center = self.tile_center(tile, tile_size);
+        let (half, quarter) = (tile_size / 2., tile_size / 4.);
+        let offsets = match self {
+            GridKind::SQUARE => vec![(-half, -half), (half, -half), (half, half), (-half, half)],
+            GridKind::HEX_POINTY => vec![(0., -half), (half, -quarter), (half, quarter), (0., half), (-half, quarter), (-half, -quarter)],
+            GridKind::HEX_FLAT => vec![(-half, 0.), (-quarter, -half), (quarter, -half), (half, 0.), (quarter, half), (-quarter, half)],
+        };
+        offsets.into_iter()
+            .map(|(x, y)| center + Vec2::new(x, y))
+            .collect()
+    }
+
+    pub fn world_size(&self, board_size: (u16, u16), tile_size: f32) -> Vec2 {
+        let (width, height) = (board_size.0 as f32, board_size.1 as f32);
+        match self {
+            GridKind::SQUARE => Vec2::new(width, height) * tile_size,
+            GridKind::HEX_POINTY => Vec2::new((width + 0.5) * tile_size, ((height - 1.).max(0.) * HEX_OVERLAP + 1.) * tile_size),
+            GridKind::HEX_FLAT => Vec2::new(((width - 1.).max(0.) * HEX_OVERLAP + 1.) * tile_size, (height + 0.5) * tile_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::glam::Vec2;
+    use super::{first_grid_line, GridKind};
+
+    #[test]
+    fn first_grid_line_is_at_or_before_the_offset() {
+        assert_eq!(first_grid_line(70., 32.), 64.);
+        assert_eq!(first_grid_line(64., 32.), 64.);
+        assert_eq!(first_grid_line(-10., 32.), -32.);
+        assert_eq!(first_grid_line(70., 0.), 70.);
+    }
+
+    #[test]
+    fn tile_centers_map_back_to_their_tile() {
+        for kind in [GridKind::SQUARE, GridKind::HEX_POINTY, GridKind::HEX_FLAT] {
+            for x in 0..4 {
+                for y in 0..4 {
+                    assert_eq!(kind.world_to_tile(kind.tile_center((x, y), 32.), 32.), (x, y), "{:?}", kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn odd_pointy_rows_are_shifted_by_half_a_tile() {
+        assert_eq!(GridKind::HEX_POINTY.tile_to_world((0, 1), 32.), Vec2::new(16., 24.));
+        assert_eq!(GridKind::HEX_FLAT.tile_to_world((1, 0), 32.), Vec2::new(24., 16.));
+    }
+
+    #[test]
+    fn neighbouring_hexes_share_an_edge() {
+        let corners = GridKind::HEX_POINTY.tile_corners((0, 0), 32.);
+        let below_right = GridKind::HEX_POINTY.tile_corners((0, 1), 32.);
+
+        assert!(below_right.contains(&corners[2]));
+        assert!(below_right.contains(&corners[3]));
+    }
+
+    #[test]
+    fn square_world_size_is_the_board_in_pixels() {
+        assert_eq!(GridKind::SQUARE.world_size((10, 5), 32.), Vec2::new(320., 160.));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,73 @@
+use crate::interact::actions::Actions;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum HelpContext {
+    ALWAYS,
+    MENU,
+    MODAL,
+    ACTION(Actions),
+}
+
+pub struct KeyBinding {
+    pub input: &'static str,
+    pub description: &'static str,
+    pub context: HelpContext,
+}
+
+pub fn key_bindings() -> Vec<KeyBinding> {
+    vec![
+        KeyBinding { input: "F1", description: "Toggle this help", context: HelpContext::ALWAYS },
+        KeyBinding { input: "F3", description: "Show the frame rate", context: HelpContext::ALWAYS },
+        KeyBinding { input: "F5", description: "Reload the theme", context: HelpContext::ALWAYS },
+        KeyBinding { input: "F6 / F7", description: "Record / replay the quick macro", context: HelpContext::ALWAYS },
+        KeyBinding { input: "G", description: "Show the tile grid", context: HelpContext::ALWAYS },
+        KeyBinding { input: "P", description: "Pause / resume the game", context: HelpContext::ALWAYS },
+        KeyBinding { input: "F8 / F9", description: "Freeze / step the messages", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Right click", description: "Actions available on a tile", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Tab / Escape", description: "Select the next creature / clear the selection", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Middle drag, Space + drag", description: "Move the camera", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Mouse wheel", description: "Zoom in / out", context: HelpContext::ALWAYS },
+        KeyBinding { input: "PageUp / PageDown", description: "Scroll the log", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Home / End", description: "Oldest / newest log lines", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Left / Right", description: "Scroll long log lines", context: HelpContext::ALWAYS },
+        KeyBinding { input: "Left click, Enter", description: "Confirm the menu option", context: HelpContext::MENU },
+        KeyBinding { input: "Up / Down", description: "Choose the menu option", context: HelpContext::MENU },
+        KeyBinding { input: "Click outside, Escape", description: "Close the window", context: HelpContext::MODAL },
+        KeyBinding { input: "Left click", description: "Choose the target to attack", context: HelpContext::ACTION(Actions::ATTACK) },
+        KeyBinding { input: "Left click", description: "Choose what to watch", context: HelpContext::ACTION(Actions::WATCH) },
+    ]
+}
+
+//Only the bindings usable right now are listed
+pub fn help_lines(gameplay_state: &Option<Actions>, menu_open: bool, modal_open: bool) -> Vec<String> {
+    key_bindings().iter()
+        .filter(|b| match &b.context {
+            HelpContext::ALWAYS => true,
+            HelpContext::MENU => menu_open,
+            HelpContext::MODAL => modal_open,
+            HelpContext::ACTION(action) => gameplay_state.as_ref() == Some(action),
+        })
+        .map(|b| format!("{} : {}", b.input, b.description))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interact::actions::Actions;
+    use super::help_lines;
+
+    #[test]
+    fn help_only_lists_the_bindings_of_the_current_context() {
+        let idle = help_lines(&None, false, false);
+        assert!(idle.contains(&"F1 : Toggle this help".to_string()));
+        assert!(!idle.contains(&"Up / Down : Choose the menu option".to_string()));
+
+        let in_menu = help_lines(&None, true, false);
+        assert!(in_menu.contains(&"Up / Down : Choose the menu option".to_string()));
+
+        let attacking = help_lines(&Some(Actions::ATTACK), false, true);
+        assert!(attacking.contains(&"Left click : Choose the target to attack".to_string()));
+        assert!(attacking.contains(&"Click outside, Escape : Close the window".to_string()));
+        assert!(!attacking.contains(&"Left click : Choose what to watch".to_string()));
+    }
+}
@@ -0,0 +1,70 @@
+//Counts consecutive frames where nothing happened, so the window can slow down when idle
+pub struct IdleDetector {
+    quiet_frames: u32,
+    threshold: u32,
+    active_this_frame: bool,
+}
+
+impl IdleDetector {
+    pub fn new(threshold: u32) -> Self {
+        IdleDetector {
+            quiet_frames: 0,
+            threshold,
+            active_this_frame: false,
+        }
+    }
+
+    //Wakes up right away, without waiting for the end of the frame
+    pub fn mark_active(&mut self) {
+        self.active_this_frame = true;
+        self.quiet_frames = 0;
+    }
+
+    pub fn end_frame(&mut self) {
+        if self.active_this_frame {
+            self.quiet_frames = 0;
+        } else {
+            self.quiet_frames = self.quiet_frames.saturating_add(1);
+        }
+        self.active_this_frame = false;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.quiet_frames >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IdleDetector;
+
+    #[test]
+    fn idle_after_enough_quiet_frames() {
+        let mut idle = IdleDetector::new(2);
+        idle.end_frame();
+        assert!(!idle.is_idle());
+
+        idle.end_frame();
+        assert!(idle.is_idle());
+    }
+
+    #[test]
+    fn activity_resets_the_count() {
+        let mut idle = IdleDetector::new(2);
+        idle.end_frame();
+        idle.end_frame();
+        idle.mark_active();
+        idle.end_frame();
+
+        assert!(!idle.is_idle());
+    }
+
+    #[test]
+    fn activity_wakes_up_before_the_frame_ends() {
+        let mut idle = IdleDetector::new(1);
+        idle.end_frame();
+        idle.mark_active();
+
+        assert!(!idle.is_idle());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use ggez::event::MouseButton;
+use ggez::input::keyboard::{KeyCode, KeyInput};
+
+pub const QUICK_MACRO: &str = "quick";
+
+//Events are queued when ggez hands them over and replayed during update
+#[derive(Debug, Clone, Copy)]
+pub enum InputEvent {
+    KeyDown(KeyInput),
+    TextInput(char),
+    MouseDown { button: MouseButton, x: f32, y: f32, space_held: bool },
+    //Motions are replayed back to back during update, the drag speed needs when they happened
+    MouseMotion { x: f32, y: f32, time: Instant },
+    MouseUp { button: MouseButton, x: f32, y: f32 },
+    //Position of the cursor, notches are positive when scrolling up
+    MouseWheel { x: f32, y: f32, notches: f32 },
+}
+
+//Named sequences of inputs, replayed through the same path as live inputs
+pub struct InputMacros {
+    macros: HashMap<String, Vec<InputEvent>>,
+    bindings: HashMap<KeyCode, String>,
+    recording: Option<(String, Vec<InputEvent>)>,
+    replaying: HashSet<String>,
+}
+
+impl InputMacros {
+    pub fn new() -> Self {
+        InputMacros {
+            macros: HashMap::new(),
+            bindings: HashMap::new(),
+            recording: None,
+            replaying: HashSet::new(),
+        }
+    }
+
+    pub fn start_recording(&mut self, name: &str) {
+        self.recording = Some((name.to_string(), vec![]));
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some((name, events)) = self.recording.take() {
+            self.macros.insert(name, events);
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        if let Some((_, events)) = self.recording.as_mut() {
+            events.push(event);
+        }
+    }
+
+    pub fn bind(&mut self, key: KeyCode, name: &str) {
+        self.bindings.insert(key, name.to_string());
+    }
+
+    pub fn bound_macro(&self, key: KeyCode) -> Option<String> {
+        self.bindings.get(&key).cloned()
+    }
+
+    pub fn events(&self, name: &str) -> Vec<InputEvent> {
+        self.macros.get(name).cloned().unwrap_or_default()
+    }
+
+    //Returns false when the macro is already running, a macro cannot call itself
+    pub fn begin_replay(&mut self, name: &str) -> bool {
+        self.replaying.insert(name.to_string())
+    }
+
+    pub fn end_replay(&mut self, name: &str) {
+        self.replaying.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::event::MouseButton;
+    use ggez::input::keyboard::KeyCode;
+    use super::{InputEvent, InputMacros};
+
+    #[test]
+    fn recorded_events_are_replayed_through_their_binding() {
+        let mut macros = InputMacros::new();
+        macros.record(InputEvent::TextInput('a'));
+        assert!(macros.events("attack").is_empty());
+
+        macros.start_recording("attack");
+        macros.record(InputEvent::MouseUp { button: MouseButton::Left, x: 10., y: 20. });
+        macros.record(InputEvent::TextInput('b'));
+        macros.stop_recording();
+        macros.bind(KeyCode::F5, "attack");
+
+        let name = macros.bound_macro(KeyCode::F5).unwrap();
+        let events = macros.events(name.as_str());
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], InputEvent::MouseUp { button: MouseButton::Left, x, y } if x == 10. && y == 20.));
+        assert!(matches!(events[1], InputEvent::TextInput('b')));
+    }
+
+    #[test]
+    fn a_running_macro_cannot_start_again() {
+        let mut macros = InputMacros::new();
+
+        assert!(macros.begin_replay("attack"));
+        assert!(!macros.begin_replay("attack"));
+        macros.end_replay("attack");
+        assert!(macros.begin_replay("attack"));
+    }
+}
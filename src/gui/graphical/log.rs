@@ -0,0 +1,107 @@
+use ggez::graphics::Color;
+use serde::{Deserialize, Serialize};
+
+//Rough width of a glyph of the default font at scale 1
+pub const LOG_CHAR_WIDTH: f32 = 8.;
+
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum LogOverflow {
+    WRAP,
+    SCROLL
+}
+
+//Set by a tag at the start of a stdout line, e.g. "[dmg] The goblin hits you"
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LogStyle {
+    DEFAULT,
+    DAMAGE,
+    HEAL,
+    INFO
+}
+
+impl LogStyle {
+    pub fn color(&self, default: Color) -> Color {
+        match self {
+            LogStyle::DEFAULT => default,
+            LogStyle::DAMAGE => Color::RED,
+            LogStyle::HEAL => Color::GREEN,
+            LogStyle::INFO => Color::new(0.6, 0.6, 0.6, 1.),
+        }
+    }
+}
+
+//Untagged lines keep the default style and are returned unchanged
+pub fn parse_log_line(line: &str) -> (LogStyle, &str) {
+    let tags = [("[dmg]", LogStyle::DAMAGE), ("[heal]", LogStyle::HEAL), ("[info]", LogStyle::INFO)];
+    for (tag, style) in tags {
+        if let Some(rest) = line.strip_prefix(tag) {
+            return (style, rest.trim_start());
+        }
+    }
+    (LogStyle::DEFAULT, line)
+}
+
+//Breaks on whitespace, words longer than a line are cut
+pub fn wrap_line(line: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines = vec![];
+    let mut current = String::new();
+
+    for word in line.split_whitespace() {
+        let mut word = word.to_string();
+        while word.chars().count() > max_chars {
+            if !current.is_empty() {
+                lines.push(current.clone());
+                current.clear();
+            }
+            let head = word.chars().take(max_chars).collect::<String>();
+            word = word.chars().skip(max_chars).collect::<String>();
+            lines.push(head);
+        }
+
+        if current.is_empty() {
+            current = word;
+        } else if current.chars().count() + 1 + word.chars().count() <= max_chars {
+            current.push(' ');
+            current.push_str(word.as_str());
+        } else {
+            lines.push(current.clone());
+            current = word;
+        }
+    }
+    lines.push(current);
+
+    lines
+}
+
+pub fn scroll_line(line: &str, offset: usize, max_chars: usize) -> String {
+    line.chars().skip(offset).take(max_chars).collect::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LogStyle, parse_log_line, scroll_line, wrap_line};
+
+    #[test]
+    fn tags_set_the_style_and_are_stripped() {
+        assert_eq!(parse_log_line("[dmg] The goblin hits you"), (LogStyle::DAMAGE, "The goblin hits you"));
+        assert_eq!(parse_log_line("[heal]You feel better"), (LogStyle::HEAL, "You feel better"));
+        assert_eq!(parse_log_line("You see a door [info]"), (LogStyle::DEFAULT, "You see a door [info]"));
+    }
+
+    #[test]
+    fn lines_wrap_on_whitespace() {
+        assert_eq!(wrap_line("a bb ccc", 4), vec!["a bb", "ccc"]);
+    }
+
+    #[test]
+    fn words_longer_than_a_line_are_cut() {
+        assert_eq!(wrap_line("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn scrolled_lines_count_characters() {
+        assert_eq!(scroll_line("héllo", 1, 3), "éll");
+        assert_eq!(scroll_line("hi", 5, 3), "");
+    }
+}
\ No newline at end of file
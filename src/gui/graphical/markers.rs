@@ -0,0 +1,69 @@
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Text};
+
+pub const MAX_EDGE_MARKERS: usize = 8;
+const EDGE_MARKER_MARGIN: f32 = 16.;
+
+pub struct EdgeMarker {
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub distance: f32,
+}
+
+impl EdgeMarker {
+    //Returns None when the target is visible, otherwise the point where the line from the
+    //viewport center to the target leaves the screen
+    pub fn from_target(viewport: (f32, f32), target: Vec2) -> Option<EdgeMarker> {
+        if target.x >= 0. && target.x <= viewport.0 && target.y >= 0. && target.y <= viewport.1 {
+            return None;
+        }
+
+        let center = Vec2::new(viewport.0, viewport.1) / 2.;
+        let delta = target - center;
+        let half_extent = (center - Vec2::splat(EDGE_MARKER_MARGIN)).max(Vec2::ZERO);
+        let scale_x = if delta.x != 0. { half_extent.x / delta.x.abs() } else { f32::MAX };
+        let scale_y = if delta.y != 0. { half_extent.y / delta.y.abs() } else { f32::MAX };
+
+        Some(EdgeMarker {
+            position: center + delta * scale_x.min(scale_y),
+            direction: delta.normalize_or_zero(),
+            distance: delta.length(),
+        })
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, tile_size: f32, color: Color) -> GameResult<()> {
+        let side = Vec2::new(-self.direction.y, self.direction.x) * 6.;
+        let tip = self.position + self.direction * 10.;
+        let base = self.position - self.direction * 6.;
+
+        let arrow = Mesh::new_polygon(ctx, DrawMode::fill(), &[tip, base + side, base - side], color)?;
+        canvas.draw(&arrow, Vec2::new(0., 0.));
+
+        canvas.draw(&Text::new(format!("{:.0}", self.distance / tile_size)),
+                    DrawParam::new()
+                        .dest(base - self.direction * 14. - Vec2::new(4., 6.))
+                        .color(color));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::glam::Vec2;
+    use super::EdgeMarker;
+
+    #[test]
+    fn visible_targets_have_no_marker() {
+        assert!(EdgeMarker::from_target((800., 600.), Vec2::new(400., 10.)).is_none());
+    }
+
+    #[test]
+    fn marker_sits_on_the_edge_towards_the_target() {
+        let marker = EdgeMarker::from_target((800., 600.), Vec2::new(1600., 300.)).unwrap();
+
+        assert!((marker.position - Vec2::new(784., 300.)).length() < 0.001);
+        assert_eq!(marker.direction, Vec2::new(1., 0.));
+        assert_eq!(marker.distance, 1200.);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,116 @@
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawMode, Mesh, MeshBuilder, Rect};
+
+const MIN_DOT_SIZE: f32 = 1.;
+
+//Drawn in the top right corner of the viewport
+pub struct Minimap {
+    pub size: Vec2,
+    pub margin: f32,
+    //A click centers the camera on the clicked point before dragging
+    pub recenter_on_click: bool,
+}
+
+impl Minimap {
+    pub fn new(size: Vec2, margin: f32) -> Self {
+        Minimap {
+            size,
+            margin,
+            recenter_on_click: true,
+        }
+    }
+
+    pub fn bounds(&self, viewport: (f32, f32)) -> Rect {
+        Rect::new(viewport.0 - self.size.x - self.margin, self.margin, self.size.x, self.size.y)
+    }
+
+    pub fn contains(&self, viewport: (f32, f32), x: f32, y: f32) -> bool {
+        let bounds = self.bounds(viewport);
+        bounds.x < x && bounds.x + bounds.w > x &&
+            bounds.y < y && bounds.y + bounds.h > y
+    }
+
+    pub fn world_to_minimap(&self, viewport: (f32, f32), world: Vec2, world_size: Vec2) -> Vec2 {
+        let bounds = self.bounds(viewport);
+        Vec2::new(bounds.x, bounds.y) + world / world_size * self.size
+    }
+
+    pub fn minimap_to_world(&self, viewport: (f32, f32), position: Vec2, world_size: Vec2) -> Vec2 {
+        let bounds = self.bounds(viewport);
+        (position - Vec2::new(bounds.x, bounds.y)) / self.size * world_size
+    }
+
+    //Converts a distance on the minimap to the matching distance in the world
+    pub fn minimap_delta_to_world(&self, delta: Vec2, world_size: Vec2) -> Vec2 {
+        delta * world_size / self.size
+    }
+
+    //A zoomed in view covers a smaller part of the world
+    pub fn viewport_rect(&self, viewport: (f32, f32), camera: Vec2, world_size: Vec2, zoom: f32) -> Rect {
+        let origin = self.world_to_minimap(viewport, camera, world_size);
+        let size = Vec2::new(viewport.0, viewport.1) / zoom / world_size * self.size;
+        Rect::new(origin.x, origin.y, size.x, size.y)
+    }
+
+    //Dots are the world position of the top left corner of each tile, sized like a scaled down tile
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, viewport: (f32, f32), camera: Vec2, world_size: Vec2, zoom: f32, dots: &[(Vec2, Color)], tile_size: f32, background_color: Color) -> GameResult<()> {
+        if world_size.x <= 0. || world_size.y <= 0. {
+            return Ok(());
+        }
+
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), self.bounds(viewport), background_color)?;
+        canvas.draw(&background, Vec2::new(0., 0.));
+
+        if !dots.is_empty() {
+            let dot_size = (Vec2::splat(tile_size) / world_size * self.size).max(Vec2::splat(MIN_DOT_SIZE));
+            let mut builder = MeshBuilder::new();
+            for (world, color) in dots {
+                let position = self.world_to_minimap(viewport, *world, world_size);
+                builder.rectangle(DrawMode::fill(), Rect::new(position.x, position.y, dot_size.x, dot_size.y), *color)?;
+            }
+            canvas.draw(&Mesh::from_data(ctx, builder.build()), Vec2::new(0., 0.));
+        }
+
+        let viewport_rect = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), self.viewport_rect(viewport, camera, world_size, zoom), Color::WHITE)?;
+        canvas.draw(&viewport_rect, Vec2::new(0., 0.));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::glam::Vec2;
+    use super::Minimap;
+
+    const VIEWPORT: (f32, f32) = (800., 600.);
+    const WORLD_SIZE: Vec2 = Vec2::new(1000., 500.);
+
+    #[test]
+    fn world_positions_are_scaled_into_the_minimap_bounds() {
+        let minimap = Minimap::new(Vec2::new(100., 50.), 10.);
+
+        assert_eq!(minimap.world_to_minimap(VIEWPORT, Vec2::new(0., 0.), WORLD_SIZE), Vec2::new(690., 10.));
+        assert_eq!(minimap.world_to_minimap(VIEWPORT, Vec2::new(500., 250.), WORLD_SIZE), Vec2::new(740., 35.));
+    }
+
+    #[test]
+    fn minimap_to_world_reverses_world_to_minimap() {
+        let minimap = Minimap::new(Vec2::new(100., 50.), 10.);
+        let world = Vec2::new(230., 470.);
+
+        let back = minimap.minimap_to_world(VIEWPORT, minimap.world_to_minimap(VIEWPORT, world, WORLD_SIZE), WORLD_SIZE);
+
+        assert!((back - world).length() < 0.001);
+    }
+
+    #[test]
+    fn viewport_rect_shrinks_when_zooming_in() {
+        let minimap = Minimap::new(Vec2::new(100., 50.), 10.);
+
+        let rect = minimap.viewport_rect(VIEWPORT, Vec2::ZERO, WORLD_SIZE, 2.);
+
+        assert!((rect.w - 40.).abs() < 0.001);
+        assert!((rect.h - 30.).abs() < 0.001);
+    }
+}
\ No newline at end of file
@@ -1,2 +1,24 @@
 pub mod window;
-pub mod sprite;
\ No newline at end of file
+pub mod sprite;
+pub mod nine_patch;
+pub mod input;
+pub mod weather;
+pub mod idle;
+pub mod theme;
+pub mod minimap;
+pub mod markers;
+pub mod combat_event;
+pub mod log;
+pub mod floating_text;
+pub mod aoe;
+pub mod text;
+pub mod help;
+pub mod rng;
+pub mod grid;
+pub mod render_stats;
+pub mod batch;
+pub mod textures;
+pub mod router;
+pub mod window_state;
+pub mod name_input;
+pub mod effects;
\ No newline at end of file
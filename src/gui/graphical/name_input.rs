@@ -0,0 +1,92 @@
+pub const MAX_PLAYER_NAME_LENGTH: usize = 16;
+
+//Text typed on the start screen, counted in characters and not in bytes
+pub struct NameInput {
+    buffer: String,
+    max_length: usize,
+}
+
+impl NameInput {
+    pub fn new(max_length: usize) -> Self {
+        NameInput {
+            buffer: String::new(),
+            max_length,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.buffer.as_str()
+    }
+
+    //Control characters (Enter, Backspace, Tab...) come through key events, not as text
+    pub fn push(&mut self, character: char) {
+        if character.is_control() || self.buffer.chars().count() >= self.max_length {
+            return;
+        }
+        self.buffer.push(character);
+    }
+
+    pub fn backspace(&mut self) {
+        self.buffer.pop();
+    }
+
+    //A blank name cannot be submitted
+    pub fn submit(&self) -> Option<String> {
+        let name = self.buffer.trim();
+        if name.is_empty() {
+            None
+        } else {
+            Some(name.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NameInput;
+
+    #[test]
+    fn typed_characters_are_appended_up_to_the_max_length() {
+        let mut input = NameInput::new(3);
+        for character in "Élise".chars() {
+            input.push(character);
+        }
+
+        assert_eq!(input.text(), "Éli");
+    }
+
+    #[test]
+    fn control_characters_are_ignored() {
+        let mut input = NameInput::new(16);
+        input.push('a');
+        input.push('\r');
+        input.push('\u{8}');
+
+        assert_eq!(input.text(), "a");
+    }
+
+    #[test]
+    fn backspace_removes_the_last_character() {
+        let mut input = NameInput::new(16);
+        input.push('a');
+        input.push('é');
+        input.backspace();
+
+        assert_eq!(input.text(), "a");
+        input.backspace();
+        input.backspace();
+        assert_eq!(input.text(), "");
+    }
+
+    #[test]
+    fn submitted_names_are_trimmed_and_never_blank() {
+        let mut input = NameInput::new(16);
+        input.push(' ');
+        assert_eq!(input.submit(), None);
+
+        for character in "Bob ".chars() {
+            input.push(character);
+        }
+        assert_eq!(input.submit(), Some("Bob".to_string()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use ggez::glam::Vec2;
+use ggez::graphics::{DrawParam, Rect};
+
+//Borders are expressed in texture pixels
+#[derive(Debug, Clone, Copy)]
+pub struct NinePatch {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl NinePatch {
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        NinePatch {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    pub fn uniform(border: f32) -> Self {
+        Self::new(border, border, border, border)
+    }
+
+    //Corners keep their size, edges stretch on one axis and the center on both
+    pub fn create_draws(&self, texture_size: (f32, f32), panel: Rect) -> Vec<DrawParam> {
+        let (texture_w, texture_h) = texture_size;
+
+        let src_x = [0., self.left, texture_w - self.right];
+        let src_w = [self.left, texture_w - self.left - self.right, self.right];
+        let src_y = [0., self.top, texture_h - self.bottom];
+        let src_h = [self.top, texture_h - self.top - self.bottom, self.bottom];
+
+        let dest_x = [panel.x, panel.x + self.left, panel.x + panel.w - self.right];
+        let dest_w = [self.left, (panel.w - self.left - self.right).max(0.), self.right];
+        let dest_y = [panel.y, panel.y + self.top, panel.y + panel.h - self.bottom];
+        let dest_h = [self.top, (panel.h - self.top - self.bottom).max(0.), self.bottom];
+
+        let mut draws = vec![];
+        for row in 0..3 {
+            for col in 0..3 {
+                let scale_x = if src_w[col] > 0. { dest_w[col] / src_w[col] } else { 0. };
+                let scale_y = if src_h[row] > 0. { dest_h[row] / src_h[row] } else { 0. };
+
+                draws.push(DrawParam::new()
+                    .src(Rect::new(src_x[col] / texture_w,
+                                   src_y[row] / texture_h,
+                                   src_w[col] / texture_w,
+                                   src_h[row] / texture_h))
+                    .dest(Vec2::new(dest_x[col], dest_y[row]))
+                    .scale(Vec2::new(scale_x, scale_y)));
+            }
+        }
+
+        draws
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::{Rect, Transform};
+    use super::NinePatch;
+
+    #[test]
+    fn corners_keep_their_size_and_the_center_stretches() {
+        let draws = NinePatch::uniform(8.).create_draws((32., 32.), Rect::new(100., 50., 200., 80.));
+
+        assert_eq!(draws.len(), 9);
+        let placement = |index: usize| match draws[index].transform {
+            Transform::Values { dest, scale, .. } => ((dest.x, dest.y), (scale.x, scale.y)),
+            Transform::Matrix(_) => panic!("nine patch draws are placed by values"),
+        };
+        assert_eq!(placement(0), ((100., 50.), (1., 1.)));
+        assert_eq!(placement(4), ((108., 58.), (184. / 16., 64. / 16.)));
+        assert_eq!(placement(8), ((292., 122.), (1., 1.)));
+        assert_eq!(draws[4].src, Rect::new(0.25, 0.25, 0.5, 0.5));
+    }
+}
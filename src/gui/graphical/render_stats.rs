@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+//Filled while drawing a frame, kept until the next one is drawn
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub sprites_drawn: u32,
+    pub sprites_culled: u32,
+    pub meshes_built: u32,
+    pub frame_time: Duration,
+}
+
+impl RenderStats {
+    //Sprites are drawn through batches, see count_draw_calls
+    pub fn count_sprite(&mut self, visible: bool) {
+        if visible {
+            self.sprites_drawn += 1;
+        } else {
+            self.sprites_culled += 1;
+        }
+    }
+
+    //A batch is drawn or culled with all its sprites
+    pub fn count_batch(&mut self, sprites: usize, visible: bool) {
+        if visible {
+            self.sprites_drawn += sprites as u32;
+            self.draw_calls += 1;
+        } else {
+            self.sprites_culled += sprites as u32;
+        }
+    }
+
+    pub fn count_meshes(&mut self, meshes: usize) {
+        self.meshes_built += meshes as u32;
+        self.draw_calls += meshes as u32;
+    }
+
+    pub fn count_draw_calls(&mut self, draw_calls: usize) {
+        self.draw_calls += draw_calls as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderStats;
+
+    #[test]
+    fn culled_batches_cost_no_draw_call() {
+        let mut stats = RenderStats::default();
+
+        stats.count_batch(100, true);
+        stats.count_batch(50, false);
+        stats.count_sprite(true);
+        stats.count_sprite(false);
+        stats.count_meshes(2);
+        stats.count_draw_calls(1);
+
+        assert_eq!((stats.sprites_drawn, stats.sprites_culled), (101, 51));
+        assert_eq!(stats.meshes_built, 2);
+        assert_eq!(stats.draw_calls, 4);
+    }
+}
@@ -0,0 +1,57 @@
+//Xorshift64, only meant for cosmetic effects so replays draw the same frames
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        //A zero state would only ever produce zeros
+        XorShift {
+            state: if seed == 0 { 0x9E3779B97F4A7C15 } else { seed },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    //Between 0 included and 1 excluded
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XorShift;
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let mut first = XorShift::new(42);
+        let mut second = XorShift::new(42);
+
+        for _ in 0..10 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+    }
+
+    #[test]
+    fn zero_seed_does_not_get_stuck() {
+        assert_ne!(XorShift::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn floats_are_between_zero_and_one() {
+        let mut rng = XorShift::new(7);
+        for _ in 0..1000 {
+            let value = rng.next_f32();
+            assert!((0. ..1.).contains(&value));
+        }
+    }
+}
\ No newline at end of file
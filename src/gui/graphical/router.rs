@@ -0,0 +1,261 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::str::from_utf8;
+use std::sync::mpsc::{Receiver, Sender};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::services::messaging::MessageContent;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Topic {
+    //Received from the gameplay
+    CLEAR,
+    STDOUT,
+    SELECT,
+    MENU,
+    SPRITE,
+    SPRITE_DELTA,
+    TARGETABLE,
+    INFO_RESPONSE,
+    GAMEPLAY_STATE,
+    AVAILABLE_ACTIONS,
+    WEATHER,
+    WATCHABLE,
+    COMBAT_EVENT,
+    AOE_PREVIEW,
+    TURN_TIMER,
+    PLAYER_HIT,
+    CONTEXT_RESPONSE,
+    TURN,
+    PING,
+    PROJECTILE,
+    //Sent to the gameplay
+    SELECT_RESPONSE,
+    INFO,
+    FRAME_TICK,
+    ACTION,
+    ACK,
+    TURN_TIMEOUT,
+    SELECTION_CHANGED,
+    MOVE,
+    CONTEXT,
+    PLAYER_NAME,
+    QUIT
+}
+
+impl Topic {
+    //Names are the keys the channels are registered with
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topic::CLEAR => "clear",
+            Topic::STDOUT => "stdout",
+            Topic::SELECT => "select",
+            Topic::MENU => "menu",
+            Topic::SPRITE => "sprite",
+            Topic::SPRITE_DELTA => "sprite_delta",
+            Topic::TARGETABLE => "targetable",
+            Topic::INFO_RESPONSE => "info_response",
+            Topic::GAMEPLAY_STATE => "gameplay_state",
+            Topic::AVAILABLE_ACTIONS => "available_actions",
+            Topic::WEATHER => "weather",
+            Topic::WATCHABLE => "watchable",
+            Topic::COMBAT_EVENT => "combat_event",
+            Topic::AOE_PREVIEW => "aoe_preview",
+            Topic::TURN_TIMER => "turn_timer",
+            Topic::PLAYER_HIT => "player_hit",
+            Topic::CONTEXT_RESPONSE => "context_response",
+            Topic::TURN => "turn",
+            Topic::PING => "ping",
+            Topic::PROJECTILE => "projectile",
+            Topic::SELECT_RESPONSE => "select_response",
+            Topic::INFO => "info",
+            Topic::FRAME_TICK => "frame_tick",
+            Topic::ACTION => "action",
+            Topic::ACK => "ack",
+            Topic::TURN_TIMEOUT => "turn_timeout",
+            Topic::SELECTION_CHANGED => "selection_changed",
+            Topic::MOVE => "move",
+            Topic::CONTEXT => "context",
+            Topic::PLAYER_NAME => "player_name",
+            Topic::QUIT => "quit",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Router {
+    receivers: HashMap<String, Receiver<MessageContent>>,
+    senders: HashMap<String, Sender<MessageContent>>,
+    prefix: String,
+    //Failures since the last call to take_errors, so the window can show them
+    errors: RefCell<Vec<String>>,
+}
+
+impl Router {
+    pub fn new(receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) -> Self {
+        Router {
+            receivers,
+            senders,
+            prefix: String::new(),
+            errors: RefCell::new(vec![]),
+        }
+    }
+
+    fn record_error(&self, error: String) {
+        log::warn!("{}", error);
+        self.errors.borrow_mut().push(error);
+    }
+
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors.take()
+    }
+
+    //A malformed message is reported and dropped, the caller keeps its current state
+    pub fn decode<T: DeserializeOwned>(&self, topic: Topic, content: &[u8]) -> Option<T> {
+        self.decode_with(topic, content, |content| bincode::deserialize(content))
+    }
+
+    //For topics whose payload has its own layout, like the versioned sprites
+    pub fn decode_with<T>(&self, topic: Topic, content: &[u8], decode: impl FnOnce(&[u8]) -> bincode::Result<T>) -> Option<T> {
+        match decode(content) {
+            Ok(decoded) => Some(decoded),
+            Err(e) => {
+                self.record_error(format!("Cannot decode message on topic {} : {}", topic.name(), e));
+                None
+            }
+        }
+    }
+
+    pub fn decode_text(&self, topic: Topic, content: &[u8]) -> Option<String> {
+        match from_utf8(content) {
+            Ok(text) => Some(text.to_string()),
+            Err(e) => {
+                self.record_error(format!("Cannot decode text on topic {} : {}", topic.name(), e));
+                None
+            }
+        }
+    }
+
+    //Lets several windows share one bus
+    pub fn set_prefix(&mut self, prefix: &str) {
+        self.prefix = prefix.to_string();
+    }
+
+    fn key(&self, topic: Topic) -> String {
+        format!("{}{}", self.prefix, topic.name())
+    }
+
+    //Missing channels and disconnected peers are reported, the window keeps running
+    pub fn send_raw(&self, topic: Topic, content: Vec<u8>) -> bool {
+        self.send_message(topic, content, None)
+    }
+
+    fn send_message(&self, topic: Topic, content: Vec<u8>, request_id: Option<u64>) -> bool {
+        let sender = match self.senders.get(&self.key(topic)) {
+            Some(sender) => sender,
+            None => {
+                self.record_error(format!("No sender for topic {}", topic.name()));
+                return false;
+            }
+        };
+
+        match sender.send(MessageContent { topic: self.key(topic), content, request_id }) {
+            Ok(_) => true,
+            Err(e) => {
+                self.record_error(format!("Cannot send on topic {} : {}", topic.name(), e));
+                false
+            }
+        }
+    }
+
+    pub fn send<T: Serialize>(&self, topic: Topic, value: &T) -> bool {
+        match bincode::serialize(value) {
+            Ok(content) => self.send_raw(topic, content),
+            Err(e) => {
+                self.record_error(format!("Cannot encode message on topic {} : {}", topic.name(), e));
+                false
+            }
+        }
+    }
+
+    //The answer is expected to echo the request id
+    pub fn send_request<T: Serialize>(&self, topic: Topic, value: &T, request_id: u64) -> bool {
+        match bincode::serialize(value) {
+            Ok(content) => self.send_message(topic, content, Some(request_id)),
+            Err(e) => {
+                self.record_error(format!("Cannot encode message on topic {} : {}", topic.name(), e));
+                false
+            }
+        }
+    }
+
+    //Messages without payload
+    pub fn signal(&self, topic: Topic) -> bool {
+        self.send_raw(topic, vec![])
+    }
+
+    pub fn try_recv_raw(&self, topic: Topic) -> Option<MessageContent> {
+        self.receivers.get(&self.key(topic))
+            .and_then(|receiver| receiver.try_recv().ok())
+    }
+
+    pub fn try_recv<T: DeserializeOwned>(&self, topic: Topic) -> Option<T> {
+        self.try_recv_raw(topic)
+            .and_then(|message| self.decode(topic, message.content.as_slice()))
+    }
+
+    pub fn try_recv_text(&self, topic: Topic) -> Option<String> {
+        self.try_recv_raw(topic)
+            .and_then(|message| self.decode_text(topic, message.content.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::mpsc::channel;
+    use super::{Router, Topic};
+
+    #[test]
+    fn typed_messages_round_trip_through_the_channels() {
+        let (sender, receiver) = channel();
+        let router = Router::new(HashMap::from([("info_response".to_string(), receiver)]),
+                                 HashMap::from([("info_response".to_string(), sender)]));
+
+        assert!(router.send(Topic::INFO_RESPONSE, &(3u16, 4u16)));
+
+        assert_eq!(router.try_recv::<(u16, u16)>(Topic::INFO_RESPONSE), Some((3, 4)));
+        assert_eq!(router.try_recv::<(u16, u16)>(Topic::INFO_RESPONSE), None);
+        assert!(router.take_errors().is_empty());
+    }
+
+    #[test]
+    fn malformed_messages_are_recorded_as_errors() {
+        let router = Router::default();
+
+        assert_eq!(router.decode::<(u16, u16)>(Topic::SELECT, &[1]), None);
+        assert_eq!(router.decode_text(Topic::STDOUT, &[0xff]), None);
+        assert!(!router.send(Topic::MOVE, &(1u16, 1u16)));
+
+        let errors = router.take_errors();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("Cannot decode message on topic select"));
+        assert!(errors[1].starts_with("Cannot decode text on topic stdout"));
+        assert_eq!(errors[2], "No sender for topic move");
+        assert!(router.take_errors().is_empty());
+    }
+
+    #[test]
+    fn prefix_is_applied_to_both_directions() {
+        let (sender, receiver) = channel();
+        let mut router = Router::new(HashMap::from([("p1/turn".to_string(), receiver)]),
+                                     HashMap::from([("p1/turn".to_string(), sender)]));
+        router.set_prefix("p1/");
+
+        assert!(router.send(Topic::TURN, &("Toto".to_string(), true)));
+
+        let message = router.try_recv_raw(Topic::TURN).unwrap();
+        assert_eq!(message.topic, "p1/turn");
+        assert_eq!(router.decode::<(String, bool)>(Topic::TURN, message.content.as_slice()), Some(("Toto".to_string(), true)));
+    }
+}
@@ -1,6 +1,5 @@
-use std::collections::BTreeMap;
 use ggez::glam::Vec2;
-use ggez::graphics::{DrawParam, Image};
+use ggez::graphics::{Color, DrawParam, Rect};
 use serde::{Deserialize, Serialize};
 
 #[derive(PartialEq, Clone, Serialize, Deserialize, Debug)]
@@ -11,30 +10,375 @@ pub enum Layer {
     PARTICLE
 }
 
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum HitShape {
+    #[default]
+    RECT,
+    CIRCLE,
+    DIAMOND
+}
+
 pub trait ObjectToSprite {
     fn get_world_origin(&self) -> Vec<Sprite>;
 }
 
+//Bincode is positional and ignores serde defaults, sprites go over the wire through encode_sprites
+//and decode_sprites so payloads from before the appended fields can still be read
 #[derive(Clone,Serialize, Deserialize, Debug)]
 pub struct Sprite {
-    pub texture_id: u8,
+    pub texture_id: u16,
     pub pos_x: i32,
     pub pos_y: i32,
-    pub layer: Layer
+    pub layer: Layer,
+    pub hit_shape: HitShape,
+    pub important: bool,
+    //Current and maximum life, for sprites standing for a creature
+    pub life: Option<(u16, u16)>,
+    //Higher values are drawn on top within a layer
+    pub z: i32,
+    //Spritesheets hold their frames side by side on a single row
+    pub frame_count: Option<u16>,
+    pub frame_duration: Option<f32>,
+    //Multiplies the texture colors, e.g. green for a poisoned creature
+    pub tint: Option<[f32; 4]>,
+    //Needed to move or remove the sprite through a SpriteDelta
+    pub id: Option<i64>
 }
 
 impl Sprite {
-    pub fn new(texture_id: u8, pos_x: i32, pos_y: i32, layer: Layer) -> Self {
+    pub fn new(texture_id: u16, pos_x: i32, pos_y: i32, layer: Layer) -> Self {
         Sprite{
             texture_id,
             pos_x,
             pos_y,
-            layer
+            layer,
+            hit_shape: HitShape::RECT,
+            important: false,
+            life: None,
+            z: 0,
+            frame_count: None,
+            frame_duration: None,
+            tint: None,
+            id: None
+        }
+    }
+
+    //None for positions no tile can have, like negative ones
+    pub fn tile(&self) -> Option<(u16, u16)> {
+        Some((u16::try_from(self.pos_x).ok()?, u16::try_from(self.pos_y).ok()?))
+    }
+
+    pub fn with_hit_shape(mut self, hit_shape: HitShape) -> Self {
+        self.hit_shape = hit_shape;
+        self
+    }
+
+    pub fn with_important(mut self, important: bool) -> Self {
+        self.important = important;
+        self
+    }
+
+    pub fn with_life(mut self, life: u16, max_life: u16) -> Self {
+        self.life = Some((life, max_life));
+        self
+    }
+
+    pub fn with_z(mut self, z: i32) -> Self {
+        self.z = z;
+        self
+    }
+
+    pub fn with_id(mut self, id: i64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn with_frames(mut self, frame_count: u16, frame_duration: f32) -> Self {
+        self.frame_count = Some(frame_count);
+        self.frame_duration = Some(frame_duration);
+        self
+    }
+
+    pub fn frame_index(elapsed: f32, frame_count: u16, frame_duration: f32) -> usize {
+        if frame_count <= 1 || frame_duration <= 0. {
+            return 0;
+        }
+        (elapsed / frame_duration) as usize % frame_count as usize
+    }
+
+    //Source rect of the frame to show, the whole image for single frame textures
+    pub fn frame_rect(&self, elapsed: f32) -> Rect {
+        match (self.frame_count, self.frame_duration) {
+            (Some(frame_count), Some(frame_duration)) if frame_count > 1 => {
+                let width = 1. / frame_count as f32;
+                let index = Self::frame_index(elapsed, frame_count, frame_duration);
+                Rect::new(index as f32 * width, 0., width, 1.)
+            }
+            _ => Rect::one()
+        }
+    }
+
+    pub fn draw_order(&self) -> (i32, i32) {
+        (self.z, self.pos_y)
+    }
+
+    pub fn life_ratio(&self) -> Option<f32> {
+        match self.life {
+            Some((life, max_life)) if max_life > 0 => Some(life as f32 / max_life as f32),
+            _ => None
         }
     }
 
-    pub fn create_drawable(&self, sprite_size: f32, sprite_textures: &BTreeMap<u8, Image>) -> (Image, DrawParam){
-        let param = DrawParam::new().dest(Vec2::new(self.pos_x as f32* sprite_size, self.pos_y as f32 * sprite_size));
-        (sprite_textures.get(&self.texture_id).unwrap().clone(), param)
+    //A creature without maximum life shows an empty bar
+    pub fn health_bar_width(life: u16, max_life: u16, full_width: f32) -> f32 {
+        if max_life == 0 {
+            return 0.;
+        }
+        full_width * (life.min(max_life) as f32 / max_life as f32)
+    }
+
+    //Coordinates are in world pixels, tested against the shape inscribed in the tile
+    //Rects are half open like the tiles, a pixel on a shared edge belongs to the tile on its right or below
+    pub fn contains(&self, x: f32, y: f32, sprite_size: f32) -> bool {
+        let half = sprite_size / 2.;
+        let dx = x - (self.pos_x as f32 * sprite_size + half);
+        let dy = y - (self.pos_y as f32 * sprite_size + half);
+
+        match self.hit_shape {
+            HitShape::RECT => dx >= -half && dx < half && dy >= -half && dy < half,
+            HitShape::CIRCLE => dx * dx + dy * dy < half * half,
+            HitShape::DIAMOND => dx.abs() + dy.abs() < half,
+        }
+    }
+
+    //The texture is looked up by id when drawing, so building draw lists does not clone images
+    pub fn create_drawable(&self, dest: Vec2) -> (u16, DrawParam){
+        let param = DrawParam::new()
+            .dest(dest)
+            .color(self.tint.map_or(Color::WHITE, Color::from));
+        (self.texture_id, param)
+    }
+}
+
+//First bytes of a versioned sprite payload, read as the length of a legacy list they would ask for billions of sprites
+const SPRITE_PAYLOAD_MAGIC: [u8; 4] = *b"SPRT";
+pub const SPRITE_PAYLOAD_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct VersionedSprites {
+    magic: [u8; 4],
+    version: u16,
+    sprites: Vec<Sprite>,
+}
+
+//Layout sent before the payloads were versioned, only a u8 texture id and the position
+//The id is widened to u16 when read, the textures keep their ids
+#[derive(Deserialize)]
+struct LegacySprite {
+    texture_id: u8,
+    pos_x: i32,
+    pos_y: i32,
+    layer: Layer,
+}
+
+impl From<LegacySprite> for Sprite {
+    fn from(legacy: LegacySprite) -> Self {
+        Sprite::new(legacy.texture_id.into(), legacy.pos_x, legacy.pos_y, legacy.layer)
+    }
+}
+
+pub fn encode_sprites(sprites: &[Sprite]) -> bincode::Result<Vec<u8>> {
+    bincode::serialize(&VersionedSprites {
+        magic: SPRITE_PAYLOAD_MAGIC,
+        version: SPRITE_PAYLOAD_VERSION,
+        sprites: sprites.to_vec(),
+    })
+}
+
+//Unversioned payloads are read with the legacy layout, the appended fields get the defaults of Sprite::new
+pub fn decode_sprites(content: &[u8]) -> bincode::Result<Vec<Sprite>> {
+    if !content.starts_with(&SPRITE_PAYLOAD_MAGIC) {
+        let legacy = bincode::deserialize::<Vec<LegacySprite>>(content)?;
+        return Ok(legacy.into_iter().map(Sprite::from).collect());
+    }
+
+    let versioned = bincode::deserialize::<VersionedSprites>(content)?;
+    if versioned.version != SPRITE_PAYLOAD_VERSION {
+        return Err(Box::new(bincode::ErrorKind::Custom(format!("unknown sprite payload version {}", versioned.version))));
+    }
+    Ok(versioned.sprites)
+}
+
+//Changes since the last sprite message, sprites are matched by id
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SpriteDelta {
+    pub added: Vec<Sprite>,
+    pub moved: Vec<(i64, i32, i32)>,
+    pub removed: Vec<i64>,
+}
+
+impl SpriteDelta {
+    //An added sprite replaces the one already having its id
+    pub fn apply(&self, sprites: &mut Vec<Sprite>) {
+        sprites.retain(|s| s.id.map_or(true, |id| !self.removed.contains(&id)));
+
+        for (id, pos_x, pos_y) in &self.moved {
+            if let Some(sprite) = sprites.iter_mut().find(|s| s.id == Some(*id)) {
+                sprite.pos_x = *pos_x;
+                sprite.pos_y = *pos_y;
+            }
+        }
+
+        for added in &self.added {
+            match added.id.and_then(|id| sprites.iter().position(|s| s.id == Some(id))) {
+                Some(index) => sprites[index] = added.clone(),
+                None => sprites.push(added.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::glam::Vec2;
+    use ggez::graphics::Color;
+    use super::{decode_sprites, encode_sprites, HitShape, Layer, Sprite, SpriteDelta};
+
+    #[test]
+    fn rect_hit_test_gives_shared_edges_to_the_tile_on_the_right_or_below() {
+        let left = Sprite::new(1, 0, 0, Layer::BACKGROUND);
+        let right = Sprite::new(1, 1, 0, Layer::BACKGROUND);
+        let below = Sprite::new(1, 0, 1, Layer::BACKGROUND);
+
+        assert!(!left.contains(32., 10., 32.));
+        assert!(right.contains(32., 10., 32.));
+        assert!(!left.contains(10., 32., 32.));
+        assert!(below.contains(10., 32., 32.));
+        assert!(left.contains(0., 0., 32.));
+    }
+
+    #[test]
+    fn circle_hit_test_excludes_the_corners() {
+        let sprite = Sprite::new(1, 0, 0, Layer::MOVABLES).with_hit_shape(HitShape::CIRCLE);
+
+        assert!(sprite.contains(16., 16., 32.));
+        assert!(!sprite.contains(1., 1., 32.));
+    }
+
+    #[test]
+    fn frame_index_loops_over_the_frames() {
+        assert_eq!(Sprite::frame_index(0.25, 4, 0.1), 2);
+        assert_eq!(Sprite::frame_index(0.45, 4, 0.1), 0);
+        assert_eq!(Sprite::frame_index(3., 1, 0.1), 0);
+    }
+
+    #[test]
+    fn health_bar_width_is_capped_by_max_life() {
+        assert_eq!(Sprite::health_bar_width(5, 10, 40.), 20.);
+        assert_eq!(Sprite::health_bar_width(15, 10, 40.), 40.);
+        assert_eq!(Sprite::health_bar_width(5, 0, 40.), 0.);
+    }
+
+    #[test]
+    fn delta_removes_moves_and_adds_sprites_by_id() {
+        let mut sprites = vec![
+            Sprite::new(1, 0, 0, Layer::MOVABLES).with_id(1),
+            Sprite::new(2, 1, 0, Layer::MOVABLES).with_id(2),
+            Sprite::new(3, 2, 0, Layer::MOVABLES).with_id(3),
+            Sprite::new(4, 3, 0, Layer::BACKGROUND),
+        ];
+        let delta = SpriteDelta {
+            added: vec![Sprite::new(9, 2, 0, Layer::MOVABLES).with_id(3), Sprite::new(5, 4, 4, Layer::MOVABLES).with_id(4)],
+            moved: vec![(1, 5, 6), (42, 0, 0)],
+            removed: vec![2],
+        };
+
+        delta.apply(&mut sprites);
+
+        let summary = sprites.iter()
+            .map(|s| (s.id, s.texture_id, s.pos_x, s.pos_y))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            (Some(1), 1, 5, 6),
+            (Some(3), 9, 2, 0),
+            (None, 4, 3, 0),
+            (Some(4), 5, 4, 4),
+        ]);
+    }
+
+    #[test]
+    fn sprites_round_trip_through_bincode() {
+        let sprite = Sprite::new(300, -1, 2, Layer::MOVABLES).with_id(7).with_z(3).with_life(4, 10);
+
+        let decoded = bincode::deserialize::<Sprite>(bincode::serialize(&sprite).unwrap().as_slice()).unwrap();
+
+        assert_eq!((decoded.texture_id, decoded.pos_x, decoded.pos_y, decoded.layer), (300, -1, 2, Layer::MOVABLES));
+        assert_eq!((decoded.id, decoded.z, decoded.life), (Some(7), 3, Some((4, 10))));
+    }
+
+    #[test]
+    fn versioned_payloads_round_trip() {
+        let sprites = vec![Sprite::new(300, -1, 2, Layer::MOVABLES).with_z(3), Sprite::new(1, 0, 0, Layer::BACKGROUND)];
+
+        let decoded = decode_sprites(encode_sprites(&sprites).unwrap().as_slice()).unwrap();
+
+        assert_eq!(decoded.iter().map(|s| (s.texture_id, s.z)).collect::<Vec<_>>(), vec![(300, 3), (1, 0)]);
+    }
+
+    //Payloads from before the versioning only have the texture id, the position and the layer
+    #[test]
+    fn legacy_payloads_are_read_with_a_zero_z() {
+        let legacy_payload = bincode::serialize(&vec![(1u8, 2i32, 3i32, Layer::MOVABLES)]).unwrap();
+
+        let decoded = decode_sprites(legacy_payload.as_slice()).unwrap();
+
+        assert_eq!((decoded[0].texture_id, decoded[0].pos_x, decoded[0].pos_y, decoded[0].layer.clone()), (1, 2, 3, Layer::MOVABLES));
+        assert_eq!(decoded[0].z, 0);
+    }
+
+    #[test]
+    fn legacy_payloads_are_drawn_without_tint() {
+        let legacy_payload = bincode::serialize(&vec![(1u8, 0i32, 0i32, Layer::MOVABLES)]).unwrap();
+
+        let decoded = decode_sprites(legacy_payload.as_slice()).unwrap();
+
+        assert_eq!(decoded[0].tint, None);
+        assert_eq!(decoded[0].create_drawable(Vec2::ZERO).1.color, Color::WHITE);
+    }
+
+    #[test]
+    fn tint_is_the_color_of_the_draw_param() {
+        let sprite = Sprite::new(1, 0, 0, Layer::MOVABLES).with_tint([0., 1., 0., 0.5]);
+
+        let decoded = decode_sprites(encode_sprites(&[sprite]).unwrap().as_slice()).unwrap();
+
+        assert_eq!(decoded[0].create_drawable(Vec2::ZERO).1.color, Color::new(0., 1., 0., 0.5));
+    }
+
+    #[test]
+    fn unknown_payload_versions_are_rejected() {
+        let mut payload = encode_sprites(&[]).unwrap();
+        payload[4] = 9;
+
+        assert!(decode_sprites(payload.as_slice()).is_err());
+    }
+
+    #[test]
+    fn draw_order_sorts_by_z_then_by_row() {
+        let mut sprites = vec![
+            Sprite::new(1, 0, 2, Layer::MOVABLES).with_z(1),
+            Sprite::new(2, 0, 5, Layer::MOVABLES),
+            Sprite::new(3, 0, 1, Layer::MOVABLES).with_z(1),
+            Sprite::new(4, 0, 3, Layer::MOVABLES),
+        ];
+
+        sprites.sort_by_key(|s| s.draw_order());
+
+        assert_eq!(sprites.iter().map(|s| s.texture_id).collect::<Vec<_>>(), vec![4, 2, 3, 1]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Text};
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextShadow {
+    pub offset: Vec2,
+    pub color: Color,
+}
+
+//The shadow is drawn first, offset behind the text, so it stays readable on busy backgrounds
+pub fn text_draws(dest: Vec2, param: DrawParam, shadow: Option<TextShadow>) -> Vec<DrawParam> {
+    let mut draws = vec![];
+    if let Some(shadow) = shadow {
+        draws.push(param.dest(dest + shadow.offset).color(shadow.color));
+    }
+    draws.push(param.dest(dest));
+    draws
+}
+
+pub fn draw_text(canvas: &mut Canvas, text: &Text, dest: Vec2, param: DrawParam, shadow: Option<TextShadow>) {
+    for draw in text_draws(dest, param, shadow) {
+        canvas.draw(text, draw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::glam::Vec2;
+    use ggez::graphics::{Color, DrawParam, Transform};
+    use super::{text_draws, TextShadow};
+
+    fn dest(param: &DrawParam) -> Vec2 {
+        match param.transform {
+            Transform::Values { dest, .. } => Vec2::new(dest.x, dest.y),
+            Transform::Matrix(_) => panic!("texts are placed by values"),
+        }
+    }
+
+    #[test]
+    fn shadow_is_drawn_offset_before_the_text() {
+        let shadow = TextShadow { offset: Vec2::new(1., 2.), color: Color::BLACK };
+
+        let draws = text_draws(Vec2::new(10., 10.), DrawParam::new().color(Color::WHITE), Some(shadow));
+
+        assert_eq!(draws.len(), 2);
+        assert_eq!((dest(&draws[0]), draws[0].color), (Vec2::new(11., 12.), Color::BLACK));
+        assert_eq!((dest(&draws[1]), draws[1].color), (Vec2::new(10., 10.), Color::WHITE));
+        assert_eq!(text_draws(Vec2::new(10., 10.), DrawParam::new(), None).len(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use ggez::{Context, GameError, GameResult};
+use ggez::graphics::{Color, Image};
+use serde::Deserialize;
+
+//Size of the placeholder for manifest entries that do not give the size of their image
+const DEFAULT_TEXTURE_SIZE: u32 = 32;
+
+//Used when no manifest is configured, with the size of the shipped images
+const DEFAULT_TEXTURES: [(u16, &str, u32, u32); 9] = [
+    (0, "/menu_background.png", 32, 32),
+    (1, "/selector.png", 32, 32),
+    (2, "/possible_area.png", 32, 32),
+    (10, "/dungeon_ground.png", 32, 32),
+    (11, "/door.png", 32, 32),
+    (12, "/door.png", 32, 32),
+    (100, "/particles.png", 128, 128),
+    (200, "/warrior.png", 32, 32),
+    (201, "/goblin.png", 32, 32),
+];
+
+//The size is the one of the image, the placeholder replacing it keeps the layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureEntry {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+    Path(String),
+    Sized { path: String, width: u32, height: u32 },
+}
+
+impl From<ManifestEntry> for TextureEntry {
+    fn from(entry: ManifestEntry) -> Self {
+        let (path, width, height) = match entry {
+            ManifestEntry::Path(path) => (path, DEFAULT_TEXTURE_SIZE, DEFAULT_TEXTURE_SIZE),
+            ManifestEntry::Sized { path, width, height } => (path, width, height),
+        };
+        TextureEntry {
+            path: format!("/{}", path.trim_start_matches('/')),
+            width,
+            height,
+        }
+    }
+}
+
+//The manifest is a json object from texture ids to image paths, like {"42": "troll.png"}
+//or {"42": {"path": "troll.png", "width": 64, "height": 64}} for images that are not tile sized
+//Paths are relative to the resource folders, with or without the leading slash
+pub fn parse_texture_manifest(content: &str) -> serde_json::Result<BTreeMap<u16, TextureEntry>> {
+    let manifest: BTreeMap<u16, ManifestEntry> = serde_json::from_str(content)?;
+    Ok(manifest.into_iter()
+        .map(|(id, entry)| (id, entry.into()))
+        .collect())
+}
+
+//A texture that cannot be loaded is replaced by a placeholder of its size so the game still starts and the hole is obvious
+pub fn load_texture_entries<T>(entries: &BTreeMap<u16, TextureEntry>, load: impl Fn(&str) -> GameResult<T>, placeholder: impl Fn(u32, u32) -> T) -> BTreeMap<u16, T> {
+    entries.iter()
+        .map(|(id, entry)| {
+            let texture = load(entry.path.as_str()).unwrap_or_else(|e| {
+                log::warn!("Cannot load texture {} : {}, using a placeholder", entry.path, e);
+                placeholder(entry.width, entry.height)
+            });
+            (*id, texture)
+        })
+        .collect()
+}
+
+fn load_images(ctx: &Context, entries: &BTreeMap<u16, TextureEntry>) -> BTreeMap<u16, Image> {
+    load_texture_entries(entries,
+                         |path| Image::from_path(ctx, path),
+                         |width, height| Image::from_color(ctx, width, height, Some(Color::MAGENTA)))
+}
+
+//The manifest is searched in the resource folders, the configured resource path included
+//A configured manifest that cannot be read is an error, the default textures would hide the mistake
+pub fn load_texture_manifest(ctx: &Context, path: &str) -> GameResult<BTreeMap<u16, Image>> {
+    let mut content = String::new();
+    ctx.fs.open(path)
+        .and_then(|mut file| file.read_to_string(&mut content).map_err(GameError::from))
+        .map_err(|e| GameError::ResourceLoadError(format!("Cannot read texture manifest {} : {}", path, e)))?;
+    let manifest = parse_texture_manifest(content.as_str())
+        .map_err(|e| GameError::ResourceLoadError(format!("Cannot parse texture manifest {} : {}", path, e)))?;
+
+    Ok(load_images(ctx, &manifest))
+}
+
+pub fn default_texture_entries() -> BTreeMap<u16, TextureEntry> {
+    DEFAULT_TEXTURES.iter()
+        .map(|(id, path, width, height)| (*id, TextureEntry { path: path.to_string(), width: *width, height: *height }))
+        .collect()
+}
+
+pub fn load_textures(ctx: &Context, manifest_path: Option<&str>) -> GameResult<BTreeMap<u16, Image>> {
+    match manifest_path {
+        Some(path) => load_texture_manifest(ctx, path),
+        None => Ok(load_images(ctx, &default_texture_entries()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use ggez::GameError;
+    use super::{default_texture_entries, load_texture_entries, parse_texture_manifest};
+
+    #[test]
+    fn manifest_entries_are_loaded_by_id() {
+        let manifest = parse_texture_manifest(r#"{"42": "troll.png", "7": "/ogre.png"}"#).unwrap();
+
+        let textures = load_texture_entries(&manifest, |path| Ok(path.to_string()), |_, _| "placeholder".to_string());
+
+        assert_eq!(textures, BTreeMap::from([(7, "/ogre.png".to_string()), (42, "/troll.png".to_string())]));
+    }
+
+    #[test]
+    fn textures_failing_to_load_get_a_placeholder_of_their_size() {
+        let manifest = parse_texture_manifest(r#"{"42": "troll.png", "43": {"path": "banner.png", "width": 160, "height": 48}}"#).unwrap();
+
+        let textures = load_texture_entries(&manifest, |path| Err(GameError::ResourceNotFound(path.to_string(), vec![])), |width, height| (width, height));
+
+        assert_eq!(textures, BTreeMap::from([(42, (32, 32)), (43, (160, 48))]));
+    }
+
+    //Nothing panics, every default texture is replaced by a placeholder as large as the shipped image
+    #[test]
+    fn nonexistent_asset_folder_gives_placeholders() {
+        let asset_folder = std::env::temp_dir().join("baston_nonexistent_assets");
+        let load = |path: &str| fs::read(asset_folder.join(path.trim_start_matches('/')))
+            .map(|_| (0, 0))
+            .map_err(GameError::from);
+
+        let textures = load_texture_entries(&default_texture_entries(), load, |width, height| (width, height));
+
+        assert_eq!(textures.len(), 9);
+        assert_eq!(textures.get(&0), Some(&(32, 32)));
+        assert_eq!(textures.get(&100), Some(&(128, 128)));
+    }
+}
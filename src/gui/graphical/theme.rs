@@ -0,0 +1,137 @@
+use std::fs;
+use ggez::glam::Vec2;
+use ggez::graphics::Color;
+use serde::{Deserialize, Serialize};
+use crate::gui::graphical::text::TextShadow;
+
+pub const THEME_PATH: &str = "theme.json";
+
+//Colors are stored as rgba arrays so the file stays easy to edit by hand
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Theme {
+    //Fills the frame before the background layer, where no tile is drawn
+    #[serde(alias = "clear_color")]
+    pub void_color: [f32; 4],
+    pub text_color: [f32; 4],
+    pub menu_text_color: [f32; 4],
+    pub text_scale: f32,
+    pub modal_backdrop_color: [f32; 4],
+    pub cursor_color: [f32; 4],
+    pub cursor_size: f32,
+    pub panel_padding: f32,
+    pub menu_line_height: f32,
+    pub menu_width: f32,
+    pub modal_width: f32,
+    pub modal_height: f32,
+    pub log_position: [f32; 2],
+    pub outline_thickness: f32,
+    pub min_outline_thickness: f32,
+    pub max_outline_thickness: f32,
+    pub text_shadow: bool,
+    pub text_shadow_color: [f32; 4],
+    pub text_shadow_offset: [f32; 2],
+    //Keyboard selection in menus and the outline of the selected tile
+    pub highlight_color: [f32; 4],
+    pub fps_color: [f32; 4],
+    pub toast_color: [f32; 4],
+    pub turn_timer_color: [f32; 4],
+    pub turn_timer_warning_color: [f32; 4],
+    pub critical_flash_color: [f32; 4],
+    pub status_flash_color: [f32; 4],
+    pub edge_marker_color: [f32; 4],
+    pub rain_color: [f32; 4],
+    pub snow_color: [f32; 4],
+    pub minimap_background_color: [f32; 4],
+    pub minimap_background_dot_color: [f32; 4],
+    pub minimap_movable_dot_color: [f32; 4],
+    pub ping_color: [f32; 4],
+    pub projectile_color: [f32; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            void_color: [0., 0., 0., 1.],
+            text_color: [1., 1., 1., 1.],
+            menu_text_color: [1., 1., 1., 1.],
+            text_scale: 1.,
+            modal_backdrop_color: [0., 0., 0., 0.5],
+            cursor_color: [1., 0., 0., 1.],
+            cursor_size: 20.,
+            panel_padding: 10.,
+            menu_line_height: 20.,
+            menu_width: 3. * 32.,
+            modal_width: 7.5 * 32.,
+            modal_height: 6.5 * 32.,
+            log_position: [200., 0.],
+            outline_thickness: 2.,
+            min_outline_thickness: 1.,
+            max_outline_thickness: 4.,
+            text_shadow: true,
+            text_shadow_color: [0., 0., 0., 0.8],
+            text_shadow_offset: [1., 1.],
+            highlight_color: [1., 1., 0., 1.],
+            fps_color: [1., 1., 0., 1.],
+            toast_color: [0.4, 0., 0., 0.8],
+            turn_timer_color: [1., 1., 1., 1.],
+            turn_timer_warning_color: [1., 0., 0., 1.],
+            critical_flash_color: [1., 1., 1., 1.],
+            status_flash_color: [0., 1., 0., 1.],
+            edge_marker_color: [1., 1., 0., 1.],
+            rain_color: [0.6, 0.7, 1., 0.6],
+            snow_color: [1., 1., 1., 0.8],
+            minimap_background_color: [0., 0., 0., 0.6],
+            minimap_background_dot_color: [0.5, 0.5, 0.5, 1.],
+            minimap_movable_dot_color: [1., 0.2, 0.2, 1.],
+            ping_color: [0.3, 0.8, 1., 1.],
+            projectile_color: [1., 0.9, 0.6, 1.],
+        }
+    }
+}
+
+impl Theme {
+    pub fn load(path: &str) -> Option<Theme> {
+        let content = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(content.as_str()) {
+            Ok(theme) => Some(theme),
+            Err(e) => {
+                log::warn!("Cannot parse theme {} : {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn load_or_default(path: &str) -> Theme {
+        Self::load(path).unwrap_or_default()
+    }
+
+    pub fn color(rgba: [f32; 4]) -> Color {
+        Color::from(rgba)
+    }
+
+    pub fn text_shadow(&self) -> Option<TextShadow> {
+        if !self.text_shadow {
+            return None;
+        }
+
+        Some(TextShadow {
+            offset: Vec2::from(self.text_shadow_offset),
+            color: Self::color(self.text_shadow_color),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Theme;
+
+    #[test]
+    fn themes_written_before_a_color_was_added_keep_its_default() {
+        let theme = serde_json::from_str::<Theme>(r#"{"clear_color": [0.1, 0.2, 0.3, 1.0]}"#).unwrap();
+
+        assert_eq!(theme.void_color, [0.1, 0.2, 0.3, 1.]);
+        assert_eq!(theme.highlight_color, Theme::default().highlight_color);
+        assert_eq!(theme.toast_color, [0.4, 0., 0., 0.8]);
+    }
+}
\ No newline at end of file
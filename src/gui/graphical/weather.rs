@@ -0,0 +1,130 @@
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder};
+use serde::{Deserialize, Serialize};
+use crate::gui::graphical::rng::XorShift;
+
+const MAX_WEATHER_PARTICLES: usize = 400;
+
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum WeatherKind {
+    NONE,
+    RAIN,
+    SNOW
+}
+
+pub struct WeatherParticle {
+    pub position: Vec2,
+    pub speed: Vec2,
+    //Closer particles (depth near 1) follow the camera more
+    pub depth: f32,
+}
+
+pub struct WeatherOverlay {
+    pub kind: WeatherKind,
+    pub intensity: f32,
+    pub particles: Vec<WeatherParticle>,
+}
+
+impl WeatherOverlay {
+    pub fn new() -> Self {
+        WeatherOverlay {
+            kind: WeatherKind::NONE,
+            intensity: 0.,
+            particles: vec![],
+        }
+    }
+
+    pub fn set(&mut self, kind: WeatherKind, intensity: f32) {
+        if kind != self.kind {
+            self.particles.clear();
+        }
+        self.kind = kind;
+        self.intensity = intensity.clamp(0., 1.);
+    }
+
+    pub fn target_count(&self) -> usize {
+        match self.kind {
+            WeatherKind::NONE => 0,
+            _ => (MAX_WEATHER_PARTICLES as f32 * self.intensity) as usize
+        }
+    }
+
+    pub fn update(&mut self, delta: f32, viewport: (f32, f32), rng: &mut XorShift) {
+        self.particles.iter_mut()
+            .for_each(|p| p.position += p.speed * delta);
+        self.particles.retain(|p| p.position.y < viewport.1);
+
+        let target_count = self.target_count();
+        self.particles.truncate(target_count);
+        while self.particles.len() < target_count {
+            let particle = self.spawn(viewport, rng);
+            self.particles.push(particle);
+        }
+    }
+
+    fn spawn(&self, viewport: (f32, f32), rng: &mut XorShift) -> WeatherParticle {
+        let depth = 0.3 + rng.next_f32() * 0.7;
+        let speed = match self.kind {
+            WeatherKind::RAIN => Vec2::new(-20., 400.),
+            _ => Vec2::new((rng.next_f32() - 0.5) * 30., 40.)
+        } * depth;
+
+        WeatherParticle {
+            position: Vec2::new(rng.next_f32() * viewport.0, -rng.next_f32() * viewport.1),
+            speed,
+            depth,
+        }
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, camera: Vec2, viewport: (f32, f32), color: Color) -> GameResult<()> {
+        if self.particles.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = MeshBuilder::new();
+        for particle in &self.particles {
+            let position = Vec2::new((particle.position.x - camera.x * particle.depth).rem_euclid(viewport.0),
+                                     particle.position.y);
+            match self.kind {
+                WeatherKind::RAIN => {
+                    builder.line(&[position, position + particle.speed.normalize() * 8. * particle.depth],
+                                 1.,
+                                 color)?;
+                }
+                _ => {
+                    builder.circle(DrawMode::fill(), position, 2. * particle.depth, 0.5, color)?;
+                }
+            }
+        }
+
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), DrawParam::new());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gui::graphical::rng::XorShift;
+    use super::{MAX_WEATHER_PARTICLES, WeatherKind, WeatherOverlay};
+
+    #[test]
+    fn particle_count_follows_the_intensity_and_falls_within_the_viewport() {
+        let mut overlay = WeatherOverlay::new();
+        let mut rng = XorShift::new(42);
+
+        overlay.set(WeatherKind::RAIN, 0.5);
+        overlay.update(0.1, (800., 600.), &mut rng);
+        assert_eq!(overlay.particles.len(), MAX_WEATHER_PARTICLES / 2);
+        assert!(overlay.particles.iter().all(|p| p.position.x >= 0. && p.position.x < 800. && p.position.y < 600.));
+
+        //Changing the weather drops the particles of the previous one
+        overlay.set(WeatherKind::SNOW, 2.);
+        assert!(overlay.particles.is_empty());
+        assert_eq!(overlay.target_count(), MAX_WEATHER_PARTICLES);
+
+        overlay.set(WeatherKind::NONE, 1.);
+        overlay.update(0.1, (800., 600.), &mut rng);
+        assert!(overlay.particles.is_empty());
+    }
+}
@@ -1,44 +1,329 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
 use std::ops::Not;
 use std::str::from_utf8;
 use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
 use std::time::{Duration, Instant};
 use ggez::{event, GameError, graphics};
-use ggez::{Context, GameResult};
-use ggez::conf::{NumSamples, WindowMode, WindowSetup};
+use ggez::{Context, ContextBuilder, GameResult};
+use ggez::conf::{Backend, NumSamples, WindowMode, WindowSetup};
 use ggez::event::MouseButton;
 use ggez::glam::Vec2;
-use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Image, Mesh, Rect, Text};
+use ggez::input::keyboard::{KeyCode, KeyInput};
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, FontData, Image, InstanceArray, Mesh, MeshBuilder, Rect, Text, TextFragment};
 use keyframe::{AnimationSequence, functions, keyframes};
 use keyframe_derive::CanTween;
-use crate::gui::graphical::sprite::{Layer, Sprite};
+use serde::{Deserialize, Serialize};
+use crate::gui::graphical::aoe::AoePreview;
+use crate::gui::graphical::batch::{BACKGROUND_CHUNK_SIZE, chunk_of, texture_runs};
+use crate::gui::graphical::combat_event::{CombatEvent, CombatEventKind};
+use crate::gui::graphical::effects::{Ping, Projectile};
+use crate::gui::graphical::floating_text::FloatingText;
+use crate::gui::graphical::grid::{first_grid_line, GridKind};
+use crate::gui::graphical::help::help_lines;
+use crate::gui::graphical::idle::IdleDetector;
+use crate::gui::graphical::input::{InputEvent, InputMacros, QUICK_MACRO};
+use crate::gui::graphical::log::{LOG_CHAR_WIDTH, LogOverflow, LogStyle, parse_log_line, scroll_line, wrap_line};
+use crate::gui::graphical::markers::{EdgeMarker, MAX_EDGE_MARKERS};
+use crate::gui::graphical::minimap::Minimap;
+use crate::gui::graphical::name_input::{MAX_PLAYER_NAME_LENGTH, NameInput};
+use crate::gui::graphical::nine_patch::NinePatch;
+use crate::gui::graphical::render_stats::RenderStats;
+use crate::gui::graphical::rng::XorShift;
+use crate::gui::graphical::router::{Router, Topic};
+use crate::gui::graphical::sprite::{decode_sprites, Layer, Sprite, SpriteDelta};
+use crate::gui::graphical::text::draw_text;
+use crate::gui::graphical::textures::load_textures;
+use crate::gui::graphical::theme::{Theme, THEME_PATH};
+use crate::gui::graphical::weather::{WeatherKind, WeatherOverlay};
+use crate::gui::graphical::window_state::{WindowState, WINDOW_STATE_PATH};
 use crate::interact::actions::Actions;
 use crate::inventory::item::{DamageTypeEnum, ItemAttackTypeEnum};
 use crate::services::animator::Animator;
 use crate::services::messaging::MessageContent;
 
-const SPRITE_SIZE: i32 = 32;
+pub const DEFAULT_TILE_SIZE: i32 = 32;
+const UI_FONT: &str = "ui";
+const DEFAULT_MAX_EFFECTS: usize = 256;
+const LOG_VISIBLE_LINES: usize = 30;
+const DEFAULT_MAX_LOG_WIDTH: f32 = 560.;
+const DEFAULT_MAX_LOG_LINES: usize = 50;
+const DEFAULT_LOG_FADE_DELAY: Duration = Duration::from_secs(5);
+const DEFAULT_LOG_MIN_ALPHA: f32 = 0.3;
+//Alpha lost per second once the log is idle
+const LOG_FADE_SPEED: f32 = 1.;
+//Characters skipped by one press of Left or Right on scrolled log lines
+const LOG_HORIZONTAL_SCROLL_STEP: i32 = 8;
+//Fraction of the camera speed lost per second once a drag is released
+const CAMERA_FRICTION: f32 = 4.;
+const MIN_CAMERA_SPEED: f32 = 5.;
+const DEFAULT_CAMERA_SMOOTHING: f32 = 5.;
+const DEFAULT_CAMERA_DEAD_ZONE: Vec2 = Vec2::new(64., 48.);
+const DEFAULT_FLASH_DURATION: Duration = Duration::from_millis(300);
+const SHAKE_AMPLITUDE: f32 = 3.;
+const DEFAULT_RNG_SEED: u64 = 42;
+const DAMAGE_SOURCE_DURATION: Duration = Duration::from_secs(1);
+const WATCHABLE_OVERLAY_ALPHA: f32 = 0.4;
+const IDLE_FRAMES_THRESHOLD: u32 = 60;
+const DEFAULT_IDLE_FPS: u32 = 10;
+const DEFAULT_TARGET_FPS: u32 = 60;
+const LOW_LIFE_THRESHOLD: f32 = 0.3;
+//Blinks per second at the threshold and when almost dead
+const LOW_LIFE_MIN_BLINK_RATE: f32 = 1.;
+const LOW_LIFE_MAX_BLINK_RATE: f32 = 5.;
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+const MAX_TOASTS: usize = 5;
+const MOVE_DURATION: Duration = Duration::from_millis(150);
+const MAX_MODALS: usize = 3;
+const MODAL_STACK_OFFSET: Vec2 = Vec2::new(16., 16.);
+const TOOLTIP_DELAY: Duration = Duration::from_millis(500);
+const MENU_CLICK_DEBOUNCE: Duration = Duration::from_millis(150);
+const DEFAULT_INFO_TIMEOUT: Duration = Duration::from_millis(250);
+const TURN_TIMER_WARNING: f32 = 5.;
+const HEALTH_BAR_HEIGHT: f32 = 4.;
+const HEALTH_BAR_MARGIN: f32 = 2.;
+const TURN_TIMER_SIZE: Vec2 = Vec2::new(200., 8.);
+const DEFAULT_MENU_POSITION: (f32, f32) = (0., 200.);
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.;
+//Zoom factor applied per wheel notch
+const ZOOM_STEP: f32 = 1.1;
+
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    pub width: f32,
+    pub height: f32,
+    pub samples: NumSamples,
+    pub title: String,
+    pub target_fps: u32,
+    //Searched for assets before the default ggez resource folders
+    pub resource_path: Option<PathBuf>,
+    //Path of a ttf file inside the resource folders, the default ggez font is used otherwise
+    pub font_path: Option<String>,
+    //Json file inside the resource folders mapping texture ids to images, the built in textures are used otherwise
+    pub texture_manifest: Option<String>,
+    pub ui_scale: f32,
+    //Shows the start screen where the player types the name of their character
+    pub ask_player_name: bool,
+    pub grid_kind: GridKind,
+    //Frames per second once nothing has happened for a while
+    pub idle_fps: u32,
+    //Prepended to every topic, so several windows can share one bus
+    pub topic_prefix: Option<String>,
+    //Seeds the cosmetic effects, the same seed replays the same shakes and weather
+    pub rng_seed: u64,
+    //How long a hovered tile waits for its info before showing that none is available
+    pub info_timeout: Duration,
+    //Keeps the important creature in view, the camera only moves once it leaves the dead zone
+    pub camera_follow: bool,
+    pub camera_smoothing: f32,
+    pub camera_dead_zone: Vec2,
+    //Particles, floating texts, pings and projectiles alive at once, the oldest is dropped beyond it
+    pub max_effects: usize,
+    pub dim_behind_modals: bool,
+    //Sends a frame tick to the gameplay after each drawn frame
+    pub emit_frame_ticks: bool,
+    //Topics whose versioned messages are acknowledged back to the gameplay
+    pub ack_topics: Vec<Topic>,
+    pub flash_duration: Duration,
+    pub shake_on_flash: bool,
+    //The low life blink of the player can be distracting, the other creatures always blink
+    pub blink_low_life_player: bool,
+    pub show_turn_timer: bool,
+    pub minimap_recenter_on_click: bool,
+    pub background_alpha: f32,
+    pub movables_alpha: f32,
+    pub ui_alpha: f32,
+    pub max_log_lines: usize,
+    //The log fades down to its minimum alpha once nothing was written for the delay
+    pub log_fade_delay: Duration,
+    pub log_min_alpha: f32,
+    pub max_log_width: f32,
+    pub log_overflow: LogOverflow,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 800.,
+            height: 600.,
+            samples: NumSamples::Four,
+            title: "baston".to_string(),
+            target_fps: DEFAULT_TARGET_FPS,
+            resource_path: None,
+            font_path: None,
+            texture_manifest: None,
+            ui_scale: 1.,
+            ask_player_name: true,
+            grid_kind: GridKind::SQUARE,
+            idle_fps: DEFAULT_IDLE_FPS,
+            topic_prefix: None,
+            rng_seed: DEFAULT_RNG_SEED,
+            info_timeout: DEFAULT_INFO_TIMEOUT,
+            camera_follow: true,
+            camera_smoothing: DEFAULT_CAMERA_SMOOTHING,
+            camera_dead_zone: DEFAULT_CAMERA_DEAD_ZONE,
+            max_effects: DEFAULT_MAX_EFFECTS,
+            dim_behind_modals: true,
+            emit_frame_ticks: false,
+            ack_topics: Vec::new(),
+            flash_duration: DEFAULT_FLASH_DURATION,
+            shake_on_flash: true,
+            blink_low_life_player: true,
+            show_turn_timer: true,
+            minimap_recenter_on_click: true,
+            background_alpha: 1.,
+            movables_alpha: 1.,
+            ui_alpha: 1.,
+            max_log_lines: DEFAULT_MAX_LOG_LINES,
+            log_fade_delay: DEFAULT_LOG_FADE_DELAY,
+            log_min_alpha: DEFAULT_LOG_MIN_ALPHA,
+            max_log_width: DEFAULT_MAX_LOG_WIDTH,
+            log_overflow: LogOverflow::WRAP,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SavedBoard {
+    pub sprites: Vec<Sprite>,
+    pub stdout: String,
+}
+
+//World bounds of the chunk the batch covers, to cull it as a whole
+struct BackgroundBatch {
+    bounds: Rect,
+    sprite_count: usize,
+    instances: InstanceArray,
+}
 
 pub struct MainState {
-    sprites_movables: Vec<(Image, DrawParam)>,
-    sprites_background: Vec<(Image, DrawParam)>,
-    sprites_ui: Vec<(Image, DrawParam)>,
-    particles: Vec<(Image, DrawParam, Instant, u8)>,
+    sprites_movables: Vec<(u16, DrawParam)>,
+    sprites_background: Vec<(u16, DrawParam)>,
+    //One instance array per texture and chunk, rebuilt only when the background changes
+    background_batches: Vec<BackgroundBatch>,
+    background_dirty: bool,
+    sprites_ui: Vec<(u16, DrawParam)>,
+    watchable_overlay: Vec<(u16, DrawParam)>,
+    aoe_preview: Option<AoePreview>,
+    particles: Vec<(u16, DrawParam, Instant, u8)>,
+    floating_texts: Vec<FloatingText>,
+    pings: Vec<Ping>,
+    projectiles: Vec<Projectile>,
+    max_effects: usize,
     animation_duration: u64,
     mouse: Mouse,
-    receivers: HashMap<String, Receiver<MessageContent>>,
-    senders: HashMap<String, Sender<MessageContent>>,
-    sprites_textures: BTreeMap<u8, Image>,
+    router: Router,
+    sprites_textures: BTreeMap<u16, Image>,
+    nine_patches: BTreeMap<u16, NinePatch>,
     stdout: String,
-    current_menu: Vec<String>,
+    log_scroll: usize,
+    log_auto_follow: bool,
+    max_log_width: f32,
+    log_overflow: LogOverflow,
+    log_horizontal_scroll: usize,
+    max_log_lines: usize,
+    last_log_message: Instant,
+    log_alpha: f32,
+    log_fade_delay: Duration,
+    log_min_alpha: f32,
     sprites: Vec<Sprite>,
+    //Every menu on screen with its top left corner, the last one is the active one
     menu_to_show: Vec<((f32, f32), Vec<String>)>,
+    //Where the last right click happened, the context menu opens there
+    context_menu_anchor: (f32, f32),
+    //Buttons of the active menu, older menus are shown but cannot be answered
     menu_buttons: Vec<Rect>,
+    last_menu_click: Option<Instant>,
+    //Tile under the cursor and since when, drives the WATCH tooltip
+    hover: Option<((u16, u16), Instant)>,
+    hover_requested: bool,
+    tooltip_open: bool,
+    //The gameplay answered an info request and waits for a single INFO signal before going on
+    watch_awaiting_release: bool,
+    //Name of the creature playing and whether it is the player
+    current_turn: Option<(String, bool)>,
+    show_fps: bool,
+    show_grid: bool,
+    //World position a creature is sliding from, keyed by sprite id
+    movements: HashMap<i64, (Vec2, Instant)>,
+    toasts: Vec<(Instant, String)>,
+    //Multiplies the theme text scale, for high density displays
+    ui_scale: f32,
+    //Name the custom font was registered with in the graphics context
+    font: Option<String>,
     selected_menu_option: Option<usize>,
-    active_modal: Option<(f32, f32, String)>,
+    //Oldest first, only the last one reacts to clicks and Escape
+    modals: Vec<(f32, f32, String)>,
+    dim_behind_modals: bool,
     gameplay_state: Option<Actions>,
+    available_actions: Vec<Actions>,
+    action_bar_buttons: Vec<Rect>,
     sprites_clicked: Vec<(f32, f32, Sprite)>,
     animator: Animator,
+    frame_count: u64,
+    emit_frame_ticks: bool,
+    //World pixels are drawn zoom times bigger, the camera stays the top left corner of the view in the world
+    zoom: f32,
+    camera: Vec2,
+    camera_velocity: Vec2,
+    //Last cursor position, its time and the button holding the drag
+    drag: Option<(Vec2, Instant, MouseButton)>,
+    drag_velocity: Vec2,
+    camera_follow: bool,
+    camera_target: Vec2,
+    camera_smoothing: f32,
+    camera_dead_zone: Vec2,
+    background_alpha: f32,
+    movables_alpha: f32,
+    ui_alpha: f32,
+    input_queue: VecDeque<InputEvent>,
+    input_macros: InputMacros,
+    weather: WeatherOverlay,
+    idle: IdleDetector,
+    idle_fps: u32,
+    theme: Theme,
+    ack_topics: HashSet<Topic>,
+    viewport: (f32, f32),
+    minimap: Minimap,
+    minimap_drag: Option<Vec2>,
+    //Keyed by sprite id, the flash follows the creature when it moves
+    flashes: HashMap<i64, (Instant, Color)>,
+    flash_duration: Duration,
+    shake_on_flash: bool,
+    //Remaining and total seconds of the current timed turn
+    turn_timer: Option<(f32, f32)>,
+    show_turn_timer: bool,
+    blink_low_life_player: bool,
+    show_help: bool,
+    //Every cosmetic random value comes from here
+    rng: XorShift,
+    shake_jitter: Vec2,
+    damage_source: Option<((u16, u16), Instant)>,
+    frozen: bool,
+    step_requested: bool,
+    //Gameplay messages wait in their channels until the game is resumed
+    paused: bool,
+    grid_kind: GridKind,
+    render_stats: RenderStats,
+    selection: Option<(u16, u16)>,
+    //Set when an info request is sent, the answer is polled on each update
+    pending_info: Option<Instant>,
+    //Id of the last info request, answers to older ones are dropped
+    info_request_id: u64,
+    info_timeout: Duration,
+    //Side of a tile in pixels, textures are expected to match it
+    tile_size: i32,
+    window_title: String,
+    target_fps: u32,
+    //Start of the last update, the next frame waits until the frame time of the current rate has passed
+    last_update: Instant,
+    //No textures and no drawing, only the message handling runs
+    headless: bool,
+    //Set while the start screen is shown, dropped once the name is sent
+    name_input: Option<NameInput>,
 }
 
 impl Default for MainState {
@@ -48,23 +333,105 @@ impl Default for MainState {
         MainState {
             sprites_movables: vec![],
             sprites_background: vec![],
+            background_batches: vec![],
+            background_dirty: false,
             sprites_ui: vec![],
+            watchable_overlay: vec![],
+            aoe_preview: None,
             particles: vec![],
+            floating_texts: vec![],
+            pings: vec![],
+            projectiles: vec![],
+            max_effects: DEFAULT_MAX_EFFECTS,
             animation_duration: 1,
             mouse: Default::default(),
-            receivers: HashMap::new(),
-            senders: HashMap::new(),
+            router: Router::default(),
             sprites_textures: Default::default(),
+            nine_patches: Default::default(),
             stdout: String::new(),
-            current_menu: vec![],
+            log_scroll: 0,
+            log_auto_follow: true,
+            max_log_width: DEFAULT_MAX_LOG_WIDTH,
+            log_overflow: LogOverflow::WRAP,
+            log_horizontal_scroll: 0,
+            max_log_lines: DEFAULT_MAX_LOG_LINES,
+            last_log_message: Instant::now(),
+            log_alpha: 1.,
+            log_fade_delay: DEFAULT_LOG_FADE_DELAY,
+            log_min_alpha: DEFAULT_LOG_MIN_ALPHA,
             sprites: vec![],
             menu_to_show: vec![],
+            context_menu_anchor: DEFAULT_MENU_POSITION,
             menu_buttons: vec![],
+            last_menu_click: None,
+            hover: None,
+            hover_requested: false,
+            tooltip_open: false,
+            watch_awaiting_release: false,
+            current_turn: None,
+            show_fps: false,
+            show_grid: false,
+            movements: HashMap::new(),
+            toasts: vec![],
+            ui_scale: 1.,
+            font: None,
             selected_menu_option: None,
-            active_modal: None,
+            modals: vec![],
+            dim_behind_modals: true,
             gameplay_state: None,
+            available_actions: vec![],
+            action_bar_buttons: vec![],
             sprites_clicked: vec![],
             animator: Animator::new(),
+            frame_count: 0,
+            emit_frame_ticks: false,
+            zoom: 1.,
+            camera: Vec2::ZERO,
+            camera_velocity: Vec2::ZERO,
+            drag: None,
+            drag_velocity: Vec2::ZERO,
+            camera_follow: false,
+            camera_target: Vec2::ZERO,
+            camera_smoothing: DEFAULT_CAMERA_SMOOTHING,
+            camera_dead_zone: DEFAULT_CAMERA_DEAD_ZONE,
+            background_alpha: 1.,
+            movables_alpha: 1.,
+            ui_alpha: 1.,
+            input_queue: VecDeque::new(),
+            input_macros: InputMacros::new(),
+            weather: WeatherOverlay::new(),
+            idle: IdleDetector::new(IDLE_FRAMES_THRESHOLD),
+            idle_fps: DEFAULT_IDLE_FPS,
+            theme: Theme::default(),
+            ack_topics: HashSet::new(),
+            viewport: (800., 600.),
+            minimap: Minimap::new(Vec2::new(160., 120.), 10.),
+            minimap_drag: None,
+            flashes: HashMap::new(),
+            flash_duration: DEFAULT_FLASH_DURATION,
+            shake_on_flash: true,
+            turn_timer: None,
+            show_turn_timer: true,
+            blink_low_life_player: true,
+            show_help: false,
+            rng: XorShift::new(DEFAULT_RNG_SEED),
+            shake_jitter: Vec2::ZERO,
+            damage_source: None,
+            frozen: false,
+            paused: false,
+            step_requested: false,
+            grid_kind: GridKind::SQUARE,
+            render_stats: RenderStats::default(),
+            selection: None,
+            pending_info: None,
+            info_request_id: 0,
+            info_timeout: DEFAULT_INFO_TIMEOUT,
+            tile_size: DEFAULT_TILE_SIZE,
+            window_title: WindowConfig::default().title,
+            target_fps: DEFAULT_TARGET_FPS,
+            last_update: Instant::now(),
+            headless: false,
+            name_input: None,
         }
     }
 }
@@ -81,372 +448,4013 @@ impl Mouse {
         self.pos_y = y;
     }
 
-    pub fn get_mesh(&self, ctx: &Context) -> Mesh {
-        Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(self.pos_x, self.pos_y, 20., 20.), Color::RED).unwrap()
+    pub fn cursor_color(action: &Option<Actions>, default_color: Color) -> Color {
+        match action {
+            Some(Actions::ATTACK) => Color::RED,
+            Some(Actions::WATCH) => Color::BLUE,
+            Some(Actions::MOVE) => Color::GREEN,
+            _ => default_color
+        }
+    }
+
+    pub fn get_mesh(&self, ctx: &Context, size: f32, action: &Option<Actions>, default_color: Color) -> Mesh {
+        Mesh::from_data(ctx, self.cursor_shape(size, action, default_color).build())
+    }
+
+    //Every shape fits in the same size x size square
+    fn cursor_shape(&self, size: f32, action: &Option<Actions>, default_color: Color) -> MeshBuilder {
+        let color = Self::cursor_color(action, default_color);
+        let origin = Vec2::new(self.pos_x, self.pos_y);
+        let mut builder = MeshBuilder::new();
+        let built = match action {
+            //A blade going down to the bottom left, with its guard
+            Some(Actions::ATTACK) => builder
+                .line(&[origin + Vec2::new(size, 0.), origin + Vec2::new(0., size)], 2., color)
+                .and_then(|b| b.line(&[origin + Vec2::new(size * 0.15, size * 0.55), origin + Vec2::new(size * 0.45, size * 0.85)], 2., color)),
+            //A lens with its handle
+            Some(Actions::WATCH) => builder
+                .circle(DrawMode::stroke(2.), origin + Vec2::splat(size * 0.4), size * 0.3, 0.5, color)
+                .and_then(|b| b.line(&[origin + Vec2::splat(size * 0.62), origin + Vec2::splat(size)], 2., color)),
+            Some(Actions::MOVE) => builder
+                .polygon(DrawMode::fill(), &[origin, origin + Vec2::new(size, size * 0.5), origin + Vec2::new(0., size)], color),
+            _ => builder
+                .rectangle(DrawMode::fill(), Rect::new(self.pos_x, self.pos_y, size, size), color)
+        };
+        built.unwrap();
+        builder
     }
 }
 
 impl MainState {
-    fn new(ctx: &Context, receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) -> GameResult<MainState> {
+    fn new(ctx: &Context, receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>, tile_size: i32, texture_manifest: Option<&str>) -> GameResult<MainState> {
         let mouse = Mouse {
             pos_y: 0.,
             pos_x: 0.,
         };
 
-        let mut textures = BTreeMap::new();
-        textures.insert(0, Image::from_path(ctx, "/menu_background.png").unwrap());
-        textures.insert(1, Image::from_path(ctx, "/selector.png").unwrap());
-        textures.insert(2, Image::from_path(ctx, "/possible_area.png").unwrap());
-        textures.insert(10, Image::from_path(ctx, "/dungeon_ground.png").unwrap());
-        textures.insert(11, Image::from_path(ctx, "/door.png").unwrap());
-        textures.insert(12, Image::from_path(ctx, "/door.png").unwrap());
-        textures.insert(100, Image::from_path(ctx, "/particles.png").unwrap());
-        textures.insert(200, Image::from_path(ctx, "/warrior.png").unwrap());
-        textures.insert(201, Image::from_path(ctx, "/goblin.png").unwrap());
+        let textures = load_textures(ctx, texture_manifest)?;
 
+        let mut nine_patches = BTreeMap::new();
+        nine_patches.insert(0, NinePatch::uniform(8.));
 
         let s = MainState {
             mouse,
-            receivers,
-            senders,
+            router: Router::new(receivers, senders),
             sprites_textures: textures,
+            nine_patches,
+            theme: Theme::load_or_default(THEME_PATH),
+            tile_size,
             ..Default::default()
         };
         Ok(s)
     }
 
-    fn draw_menu(&mut self, canvas: &mut Canvas, x: f32, y: f32, options: Vec<String>) -> GameResult<()> {
-        canvas.draw(self.sprites_textures.get(&(0 as u8))
-                        .unwrap(),
-                    DrawParam::new()
-                        .dest(Vec2::new(x, y))
-                        .scale(Vec2::new(5f32, 5f32)));
-
-        options.iter()
-            .enumerate()
-            .for_each(|(i, el)| {
-                self.menu_buttons.push(Rect::new(x + 10., (y + i as f32 * 20.) + 10.0, 3. * 32., 15.));
-
-                canvas.draw(&Text::new(el),
-                            graphics::DrawParam::from([x, y])
-                                .color(Color::WHITE)
-                                .scale(Vec2::new(1., 1.))
-                                .dest(Vec2::new(x + 10., (y + i as f32 * 20.) + 10.)));
-            });
+    pub fn new_headless(receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) -> MainState {
+        MainState {
+            router: Router::new(receivers, senders),
+            headless: true,
+            ..Default::default()
+        }
+    }
 
-        Ok(())
+    pub fn is_headless(&self) -> bool {
+        self.headless
     }
 
-    fn draw_modal(&mut self, canvas: &mut Canvas, x: f32, y: f32, content: &str) -> GameResult<()> {
-        canvas.draw(self.sprites_textures.get(&(0 as u8))
-                        .unwrap(),
-                    DrawParam::new()
-                        .dest(Vec2::new(x, y))
-                        .scale(Vec2::new(7.5f32, 6.5f32)));
-
-        canvas.draw(&Text::new(content),
-                    graphics::DrawParam::from([x, y])
-                        .color(Color::WHITE)
-                        .scale(Vec2::new(1., 1.))
-                        .dest(Vec2::new(x + 10., y + 10.)));
-        Ok(())
+    //Same message handling as a frame update, without needing a context
+    pub fn process_messages(&mut self) {
+        self.process_channels_if_running();
+        self.process_input_queue();
+        self.surface_router_errors();
     }
 
-    fn mouse_hovering_characterisation(&mut self, x: f32, y: f32, sprites: Vec<Sprite>) {
-        if let Some(gameplay_state) = self.gameplay_state.clone() {
-            match gameplay_state {
-                Actions::WATCH => self.watch_action(&x, &y, sprites),
-                Actions::ATTACK => self.attack_action(&x, &y, sprites),
-                _ => ()
+    fn set_sprites(&mut self, mut sprites: Vec<Sprite>) {
+        //A move interrupting a slide starts from where the creature is drawn, not from its last tile
+        let previous_positions = self.sprites.iter()
+            .filter(|s| s.layer == Layer::MOVABLES)
+            .filter_map(|s| s.id.map(|id| (id, ((s.pos_x, s.pos_y), self.slide_position(s)))))
+            .collect::<HashMap<i64, ((i32, i32), Vec2)>>();
+        for sprite in sprites.iter().filter(|s| s.layer == Layer::MOVABLES) {
+            if let Some(id) = sprite.id {
+                match previous_positions.get(&id) {
+                    Some(&(tile, drawn)) if tile != (sprite.pos_x, sprite.pos_y) => {
+                        self.movements.insert(id, (drawn, Instant::now()));
+                    }
+                    _ => ()
+                }
             }
         }
+
+        //Stable sort, sprites in the same order keep the order they were sent in
+        sprites.sort_by_key(|s| s.draw_order());
+        self.sprites_movables = sprites.iter()
+            .filter(|s| s.layer == Layer::MOVABLES)
+            .map(|e| self.sprite_drawable(e))
+            .collect::<Vec<(u16, DrawParam)>>();
+
+        self.sprites_background = sprites.iter()
+            .filter(|s| s.layer == Layer::BACKGROUND)
+            .map(|e| self.sprite_drawable(e))
+            .collect::<Vec<(u16, DrawParam)>>();
+
+        self.sprites_ui = sprites.iter()
+            .filter(|s| s.layer == Layer::UI)
+            .map(|e| self.sprite_drawable(e))
+            .collect::<Vec<(u16, DrawParam)>>();
+
+        self.sprites = sprites;
+        self.background_dirty = true;
     }
 
-    fn set_gameplay_state(&mut self) {
-        if let Ok(state_content) = self.receivers.get("gameplay_state").unwrap().try_recv() {
-            self.gameplay_state = Some(bincode::deserialize(state_content.content.as_slice()).unwrap());
+    //Draw lists are built with the same filter, so they zip with the sprites they come from
+    fn rebuild_background_batches(&mut self, ctx: &Context) {
+        let mut grouped: BTreeMap<((i32, i32), u16), Vec<DrawParam>> = BTreeMap::new();
+        let background = self.sprites.iter().filter(|s| s.layer == Layer::BACKGROUND);
+        for (sprite, (texture_id, param)) in background.zip(&self.sprites_background) {
+            grouped.entry((chunk_of((sprite.pos_x, sprite.pos_y)), *texture_id))
+                .or_default()
+                .push(Self::with_layer_alpha(*param, self.background_alpha));
         }
-    }
 
-    fn attack_action(&mut self, x: &f32, y: &f32, sprites: Vec<Sprite>) {
-        //Send click position info
-        self.send_info_message(&x, &y);
+        self.background_batches = grouped.into_iter()
+            .filter_map(|((chunk, texture_id), params)| {
+                let mut instances = InstanceArray::new(ctx, self.texture(texture_id)?.clone());
+                let sprite_count = params.len();
+                params.into_iter().for_each(|param| instances.push(param));
+                Some(BackgroundBatch {
+                    bounds: self.chunk_bounds(chunk),
+                    sprite_count,
+                    instances,
+                })
+            })
+            .collect();
+        self.background_dirty = false;
     }
 
-    fn send_info_message(&mut self, x: &f32, y: &f32) {
-        self.senders.get("info").unwrap().send(MessageContent {
-            topic: "info".to_string(),
-            content: bincode::serialize(&((x / SPRITE_SIZE as f32).floor() as u16, (y / SPRITE_SIZE as f32).floor() as u16)).unwrap(),
-        }).unwrap();
+    //One more tile on each axis covers the offset rows or columns of hex grids
+    fn chunk_bounds(&self, chunk: (i32, i32)) -> Rect {
+        let tile_size = self.tile_size as f32;
+        let origin = self.grid_kind.tile_to_world((chunk.0 * BACKGROUND_CHUNK_SIZE, chunk.1 * BACKGROUND_CHUNK_SIZE), tile_size);
+        let extent = (BACKGROUND_CHUNK_SIZE + 1) as f32 * tile_size;
+        Rect::new(origin.x, origin.y, extent, extent)
     }
 
-    fn watch_action(&mut self, x: &f32, y: &f32, sprites: Vec<Sprite>) {
-        //Send click position info
-        self.send_info_message(&x, &y);
-        self.sprites_clicked = sprites.iter()
-            .map(|s| (x.clone(), y.clone(), s.clone()))
-            .collect::<Vec<(f32, f32, Sprite)>>();
+    pub fn save_board(&self, path: &str) -> std::io::Result<()> {
+        let board = SavedBoard {
+            sprites: self.sprites.clone(),
+            stdout: self.stdout.clone(),
+        };
+        let content = bincode::serialize(&board)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(path, content)
     }
 
-    fn get_all_targetables_cell_to_sprites(&self) -> Vec<Sprite> {
-        //Get all targetables cells
-        let targetables_receiver = self.receivers.get("targetable").unwrap();
-        let targetable_coordinates: Vec<Vec<bool>> = if let Ok(targetable) = targetables_receiver.try_recv() {
-            bincode::deserialize(targetable.content.as_slice()).unwrap()
+    //A missing or corrupt save is not an error, we just wait for the gameplay broadcast
+    pub fn load_board(&mut self, path: &str) {
+        let board = fs::read(path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<SavedBoard>(bytes.as_slice()).ok());
+
+        if let Some(board) = board {
+            self.set_sprites(board.sprites);
+            self.stdout = board.stdout;
         } else {
-            Vec::new()
-        };
-        targetable_coordinates.iter()
-            .enumerate()
-            .flat_map(|(y, row)| {
-                row.iter().enumerate()
-                    .filter(|(x, &cell)| cell)
-                    .map(|(x, &cell)|
-                        Sprite::new(2, x as i32, y as i32, Layer::UI)
-                    )
-                    .collect::<Vec<Sprite>>()
-            })
-            .collect::<Vec<Sprite>>()
+            log::warn!("Cannot load board from {}, starting empty", path);
+        }
     }
 
-    fn wait_for_watch(&mut self) {
-        let hovering_info =
-            if let Ok(response) = self.receivers.get("info_response").unwrap().try_recv() {
-                Some(format!("{}", from_utf8(response.content.as_slice()).unwrap()))
-            } else {
-                None
-            };
+    pub fn set_log_overflow(&mut self, max_log_width: f32, log_overflow: LogOverflow) {
+        self.max_log_width = max_log_width;
+        self.log_overflow = log_overflow;
+        self.log_horizontal_scroll = 0;
+    }
 
-        if let Some(info) = hovering_info {
-            self.active_modal = {
-                if self.sprites_clicked.is_empty().not() {
-                    let first_element = self.sprites_clicked.first().unwrap();
-                    Some((first_element.0.clone(), first_element.1.clone(), info.to_string()))
-                } else {
-                    None
-                }
-            };
+    fn log_max_chars(&self) -> usize {
+        (self.max_log_width / (LOG_CHAR_WIDTH * self.text_scale())).floor() as usize
+    }
 
-            self.clear_after_turn();
+    //Computed on the fly so any change of width or text scale is picked up
+    //Wrapped parts of a line keep the style of its tag
+    fn display_log_lines(&self) -> Vec<(LogStyle, String)> {
+        let max_chars = self.log_max_chars();
+        let lines = self.stdout.lines().map(parse_log_line);
+        match self.log_overflow {
+            LogOverflow::WRAP => lines
+                .flat_map(|(style, line)| wrap_line(line, max_chars).into_iter().map(move |l| (style, l)))
+                .collect::<Vec<(LogStyle, String)>>(),
+            LogOverflow::SCROLL => lines
+                .map(|(style, line)| (style, scroll_line(line, self.log_horizontal_scroll, max_chars)))
+                .collect::<Vec<(LogStyle, String)>>()
         }
     }
 
-    fn clear_after_turn(&mut self) {
-        self.sprites_clicked.clear();
-        self.sprites_ui.clear();
-        self.gameplay_state = None;
+    //Offset in characters, stops once the end of the longest line is visible
+    fn scroll_log_horizontally(&mut self, offset: i32) {
+        if self.log_overflow == LogOverflow::SCROLL {
+            let longest = self.stdout.lines()
+                .map(|line| parse_log_line(line).1.chars().count())
+                .max()
+                .unwrap_or(0);
+            let max_scroll = longest.saturating_sub(self.log_max_chars()) as i32;
+            self.log_horizontal_scroll = (self.log_horizontal_scroll as i32 + offset).clamp(0, max_scroll) as usize;
+        }
     }
 
-    fn wait_for_attack(&mut self) {
-        if let Ok(response) = self.receivers.get("info_response").unwrap().try_recv() {
-            if let Ok(ending_attack_turn) = from_utf8( response.content.as_slice()) {
-                if ending_attack_turn == "end_attack" {
-                    self.clear_after_turn();
-                    return;
-                }
-            }
-            if let Ok(target_position) = bincode::deserialize::<((u16, u16), DamageTypeEnum)>(response.content.as_slice()) {
+    fn max_log_scroll(&self) -> usize {
+        self.display_log_lines().len().saturating_sub(LOG_VISIBLE_LINES)
+    }
 
-                let sprite = Sprite::new(1, target_position.0.0 as i32, target_position.0.1 as i32, Layer::UI);
-                self.sprites_ui.append(&mut vec![sprite.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures)]);
-                let attack_particle = Sprite::new(100, target_position.0.0 as i32, target_position.0.1 as i32, Layer::PARTICLE)
-                    .create_drawable(SPRITE_SIZE as f32, &self.sprites_textures);
+    //Scroll is counted in lines from the newest message
+    fn scroll_log_up(&mut self, lines: usize) {
+        self.log_scroll = (self.log_scroll + lines).min(self.max_log_scroll());
+        self.log_auto_follow = self.log_scroll == 0;
+    }
 
-                let damage_type = match target_position.1 {
-                    DamageTypeEnum::SLASHING => 1,
-                    DamageTypeEnum::FIRE => 0,
-                    _ => 0
-                };
+    fn scroll_log_down(&mut self, lines: usize) {
+        self.log_scroll = self.log_scroll.saturating_sub(lines);
+        self.log_auto_follow = self.log_scroll == 0;
+    }
 
-                self.particles.push((attack_particle.0, attack_particle.1, Instant::now(), damage_type));
-            }
-        } else {
-            let mut targetable_cells = self.get_all_targetables_cell_to_sprites();
-            self.sprites_ui.append(&mut targetable_cells.iter()
-                .filter(|s| s.layer == Layer::UI)
-                .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures))
-                .collect::<Vec<(Image, DrawParam)>>());
+    fn visible_log(&self) -> Vec<(LogStyle, String)> {
+        let lines = self.display_log_lines();
+        let end = lines.len().saturating_sub(self.log_scroll);
+        let start = end.saturating_sub(LOG_VISIBLE_LINES);
+        lines[start..end].to_vec()
+    }
 
-            self.sprites.append(&mut targetable_cells);
+    //Lines are drawn one by one, each with the color of its style
+    fn draw_log(&self, canvas: &mut Canvas) {
+        let shadow = self.theme.text_shadow().map(|mut shadow| {
+            shadow.color.a *= self.log_alpha;
+            shadow
+        });
+        let origin = Vec2::from(self.theme.log_position);
+        let line_height = self.theme.menu_line_height * self.text_scale();
+        let default_color = Theme::color(self.theme.text_color);
+
+        for (i, (style, line)) in self.visible_log().iter().enumerate() {
+            draw_text(canvas,
+                      &self.ui_text(line.as_str()),
+                      origin + Vec2::new(0., i as f32 * line_height),
+                      Self::with_layer_alpha(graphics::DrawParam::new()
+                                                 .color(style.color(default_color))
+                                                 .scale(Vec2::splat(self.text_scale())),
+                                             self.log_alpha),
+                      shadow);
         }
     }
-}
 
+    pub fn set_max_log_lines(&mut self, max_log_lines: usize) {
+        self.max_log_lines = max_log_lines;
+        self.trim_log();
+    }
 
-impl event::EventHandler<ggez::GameError> for MainState {
-    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> Result<(), GameError> {
-        if button != MouseButton::Left {
-            return Ok(());
+    //Oldest lines are dropped first
+    fn trim_log(&mut self) {
+        let lines = self.stdout.lines().collect::<Vec<&str>>();
+        if lines.len() > self.max_log_lines {
+            self.stdout = lines[lines.len() - self.max_log_lines..].join("\n");
         }
+        self.log_scroll = self.log_scroll.min(self.max_log_scroll());
+    }
 
-        //If some modal exist, we close it on click
-        if let Some(a_m) = self.active_modal.clone() {
-            self.senders.get("info").unwrap().send(MessageContent {
-                topic: "info".to_string(),
-                content: vec![],
-            }).unwrap();
+    pub fn set_log_fade(&mut self, log_fade_delay: Duration, log_min_alpha: f32) {
+        self.log_fade_delay = log_fade_delay;
+        self.log_min_alpha = log_min_alpha.clamp(0., 1.);
+    }
 
-            self.active_modal = None;
-            return Ok(());
+    fn is_hovering_log(&self) -> bool {
+        let (x, y) = (self.theme.log_position[0], self.theme.log_position[1]);
+        let height = LOG_VISIBLE_LINES as f32 * self.theme.menu_line_height * self.text_scale();
+        self.mouse.pos_x > x && self.mouse.pos_x < x + self.max_log_width &&
+            self.mouse.pos_y > y && self.mouse.pos_y < y + height
+    }
+
+    fn update_log_alpha(&mut self, delta: f32) {
+        if self.is_hovering_log() || self.last_log_message.elapsed() < self.log_fade_delay {
+            self.log_alpha = 1.;
+        } else {
+            self.log_alpha = (self.log_alpha - LOG_FADE_SPEED * delta).max(self.log_min_alpha);
         }
+    }
 
-        let button_clicked = self.menu_buttons.iter()
-            .filter(|b| b.x < x && b.x + b.w > x &&
-                b.y < y && b.y + b.h > y)
-            .map(|el| el.clone())
-            .collect::<Vec<Rect>>();
-
-        if button_clicked.len() > 0 {
-            self.selected_menu_option = self.menu_buttons.iter()
-                .position(|b| b.x < x && b.x + b.w > x &&
-                    b.y < y && b.y + b.h > y);
-
-            if let Some(menu_option) = self.selected_menu_option {
-                self.senders.get("select_response").unwrap().send(MessageContent {
-                    topic: "select_response".to_string(),
-                    content: bincode::serialize(&menu_option).unwrap(),
-                }).unwrap();
+    pub fn set_max_effects(&mut self, max_effects: usize) {
+        self.max_effects = max_effects;
+        self.enforce_effects_budget();
+    }
+
+    pub fn active_effects_count(&self) -> usize {
+        self.particles.len() + self.floating_texts.len() + self.pings.len() + self.projectiles.len()
+    }
+
+    fn push_particle(&mut self, particle: (u16, DrawParam, Instant, u8)) {
+        self.particles.push(particle);
+        self.enforce_effects_budget();
+    }
+
+    fn push_floating_text(&mut self, floating_text: FloatingText) {
+        self.floating_texts.push(floating_text);
+        self.enforce_effects_budget();
+    }
+
+    fn push_ping(&mut self, ping: Ping) {
+        self.pings.push(ping);
+        self.enforce_effects_budget();
+    }
+
+    fn push_projectile(&mut self, projectile: Projectile) {
+        self.projectiles.push(projectile);
+        self.enforce_effects_budget();
+    }
+
+    //Each list is stored oldest first, the oldest of the four fronts goes first when over budget
+    fn enforce_effects_budget(&mut self) {
+        while self.active_effects_count() > self.max_effects {
+            let fronts = [
+                self.particles.first().map(|p| p.2),
+                self.floating_texts.first().map(|t| t.start),
+                self.pings.first().map(|p| p.start),
+                self.projectiles.first().map(|p| p.start),
+            ];
+            let oldest = fronts.iter()
+                .enumerate()
+                .filter_map(|(i, start)| start.map(|start| (i, start)))
+                .min_by_key(|(_, start)| *start)
+                .map(|(i, _)| i);
+            match oldest {
+                Some(0) => { self.particles.remove(0); }
+                Some(1) => { self.floating_texts.remove(0); }
+                Some(2) => { self.pings.remove(0); }
+                Some(_) => { self.projectiles.remove(0); }
+                None => break
             }
-            return Ok(());
         }
+    }
 
-        let sprites_selected = self.sprites.iter()
-            .filter(|s| s.pos_y * SPRITE_SIZE < y as i32 && s.pos_y * SPRITE_SIZE + SPRITE_SIZE > y as i32 &&
-                s.pos_x * SPRITE_SIZE < x as i32 && s.pos_x * SPRITE_SIZE + SPRITE_SIZE > x as i32)
-            .map(|e| e.clone())
-            .collect::<Vec<Sprite>>();
+    fn draw_panel(&self, canvas: &mut Canvas, texture_id: u16, panel: Rect) {
+        let texture = match self.texture(texture_id) {
+            Some(texture) => texture,
+            None => {
+                log::warn!("No texture {} for the panel background, skipping it", texture_id);
+                return;
+            }
+        };
+        let texture_size = (texture.width() as f32, texture.height() as f32);
 
-        //We check if user has clicked on something interactable and if interactions are availables
-        if !sprites_selected.is_empty() {
-            self.mouse_hovering_characterisation(x, y, sprites_selected);
+        if let Some(nine_patch) = self.nine_patches.get(&texture_id) {
+            nine_patch.create_draws(texture_size, panel)
+                .into_iter()
+                .for_each(|param| canvas.draw(texture, param));
+        } else {
+            canvas.draw(texture,
+                        DrawParam::new()
+                            .dest(Vec2::new(panel.x, panel.y))
+                            .scale(Vec2::new(panel.w / texture_size.0, panel.h / texture_size.1)));
         }
+    }
 
+    pub fn reload_theme(&mut self) {
+        if let Some(theme) = Theme::load(THEME_PATH) {
+            self.theme = theme;
+        }
+    }
 
-        Ok(())
+    fn menu_text_param(&self, x: f32, y: f32, i: usize) -> (Vec2, DrawParam) {
+        let padding = self.theme.panel_padding;
+        (Vec2::new(x + padding, (y + i as f32 * self.theme.menu_line_height) + padding),
+         graphics::DrawParam::new()
+             .color(Theme::color(self.theme.menu_text_color))
+             .scale(Vec2::splat(self.text_scale())))
     }
 
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        let point2 = ctx.mouse.position();
-        self.set_gameplay_state();
+    fn menu_size(&self, options: usize) -> Vec2 {
+        let padding = self.theme.panel_padding;
+        Vec2::new(self.theme.menu_width + 2. * padding, options as f32 * self.theme.menu_line_height + 2. * padding)
+    }
+
+    //Menus anchored near the right or bottom edge open towards the inside of the window
+    pub fn clamp_menu_position(anchor: (f32, f32), menu_size: Vec2, viewport: (f32, f32)) -> (f32, f32) {
+        let x = anchor.0.min(viewport.0 - menu_size.x).max(0.);
+        let y = anchor.1.min(viewport.1 - menu_size.y).max(0.);
+        (x, y)
+    }
+
+    //One rect per option, where the option is drawn
+    fn menu_button_rects(&self, anchor: (f32, f32), options: usize) -> Vec<Rect> {
+        let padding = self.theme.panel_padding;
+        let line_height = self.theme.menu_line_height;
+        let (x, y) = Self::clamp_menu_position(anchor, self.menu_size(options), self.viewport);
+        (0..options)
+            .map(|i| Rect::new(x + padding, (y + i as f32 * line_height) + padding, self.theme.menu_width, line_height - 5.))
+            .collect()
+    }
+
+    //Hit rects are rebuilt on each draw so they match the menu on screen, only the active menu is clickable
+    fn layout_menu_buttons(&mut self) {
+        self.menu_buttons = match self.menu_to_show.last() {
+            Some((anchor, options)) => self.menu_button_rects(*anchor, options.len()),
+            None => vec![]
+        };
+    }
+
+    //Only the active menu shows the keyboard selection
+    fn draw_menu(&mut self, ctx: &Context, canvas: &mut Canvas, anchor: (f32, f32), options: Vec<String>, active: bool) -> GameResult<()> {
+        let size = self.menu_size(options.len());
+        let (x, y) = Self::clamp_menu_position(anchor, size, self.viewport);
+        self.draw_panel(canvas, 0, Rect::new(x, y, size.x, size.y));
 
-        if let Some(clear_container) = self.receivers.get("clear") {
-            if let Ok(clear) = clear_container.try_recv() {
-                self.stdout.clear();
+        let buttons = self.menu_button_rects(anchor, options.len());
+        for (i, (el, button)) in options.iter().zip(buttons).enumerate() {
+            let (dest, param) = self.menu_text_param(x, y, i);
+            if active && self.selected_menu_option == Some(i) {
+                let highlight = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), button, Theme::color(self.theme.highlight_color))?;
+                canvas.draw(&highlight, Vec2::new(0., 0.));
             }
+
+            draw_text(canvas, &self.ui_text(el), dest, param, self.theme.text_shadow());
         }
 
-        //Get stdout
-        if let Some(stdout_container) = self.receivers.get("stdout") {
-            if let Ok(text) = stdout_container.try_recv() {
-                let out = format!("{}\n{}", self.stdout, from_utf8(text.content.as_slice()).unwrap());
-                self.stdout = out;
-            }
+        Ok(())
+    }
+
+    //The theme size is the smallest modal, longer content grows it line by line
+    fn modal_size(&self, content: &str) -> Vec2 {
+        let padding = self.theme.panel_padding;
+        let line_height = self.theme.menu_line_height * self.text_scale();
+        let lines = content.lines().count();
+        let longest = content.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        Vec2::new((longest as f32 * LOG_CHAR_WIDTH as f32 * self.text_scale() + 2. * padding).max(self.theme.modal_width),
+                  (lines as f32 * line_height + 2. * padding).max(self.theme.modal_height))
+    }
+
+    fn modal_rect(&self, x: f32, y: f32, content: &str) -> Rect {
+        let size = self.modal_size(content);
+        Rect::new(x, y, size.x, size.y)
+    }
+
+    //A second signal would be read by the gameplay as the next tile to watch
+    fn release_watch(&mut self) {
+        if self.watch_awaiting_release {
+            self.watch_awaiting_release = false;
+            self.router.signal(Topic::INFO);
         }
+    }
 
-        //Get menu
-        if let Some(select_container) = self.receivers.get("select") {
-            if let Ok(text) = select_container.try_recv() {
-                self.current_menu = from_utf8(text.content.as_slice())
-                    .unwrap()
-                    .split(":")
-                    .map(|el| el.to_string())
-                    .collect();
-            }
+    //The watch is released once the last modal is gone, whichever way they were removed
+    fn remove_modal(&mut self, index: usize) {
+        if index >= self.modals.len() {
+            return;
+        }
+
+        self.modals.remove(index);
+        if self.modals.is_empty() {
+            self.tooltip_open = false;
+            self.release_watch();
+        }
+    }
+
+    fn close_modal(&mut self) {
+        if !self.modals.is_empty() {
+            self.remove_modal(self.modals.len() - 1);
+        }
+    }
+
+    //Each modal is shifted from the previous one so the ones below stay readable
+    fn push_modal(&mut self, x: f32, y: f32, content: String) {
+        let offset = MODAL_STACK_OFFSET * self.modals.len().min(MAX_MODALS - 1) as f32;
+        self.modals.push((x + offset.x, y + offset.y, content));
+        if self.modals.len() > MAX_MODALS {
+            self.remove_modal(0);
+        }
+    }
+
+    fn draw_modal(&mut self, canvas: &mut Canvas, x: f32, y: f32, content: &str) -> GameResult<()> {
+        if content.trim().is_empty() {
+            return Ok(());
         }
 
-        //Get sprites
-        if let Some(receiver) = self.receivers.get("sprite") {
-            if let Ok(sprites) = receiver.try_recv() {
-                let sprites: Vec<Sprite> = bincode::deserialize(sprites.content.as_slice()).unwrap();
+        let padding = self.theme.panel_padding;
+        self.draw_panel(canvas, 0, self.modal_rect(x, y, content));
 
-                self.sprites_movables = sprites.iter()
-                    .filter(|s| s.layer == Layer::MOVABLES)
-                    .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures))
-                    .collect::<Vec<(Image, DrawParam)>>();
+        draw_text(canvas,
+                  &self.ui_text(content),
+                  Vec2::new(x + padding, y + padding),
+                  graphics::DrawParam::new()
+                      .color(Theme::color(self.theme.text_color))
+                      .scale(Vec2::splat(self.text_scale())),
+                  self.theme.text_shadow());
+        Ok(())
+    }
 
-                self.sprites_background = sprites.iter()
-                    .filter(|s| s.layer == Layer::BACKGROUND)
-                    .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures))
-                    .collect::<Vec<(Image, DrawParam)>>();
+    fn draw_help(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let lines = help_lines(&self.gameplay_state, !self.menu_to_show.is_empty(), !self.modals.is_empty());
+        let padding = self.theme.panel_padding;
+        let line_height = self.theme.menu_line_height;
+        let panel = Rect::new(self.viewport.0 / 4.,
+                              self.viewport.1 / 4.,
+                              self.viewport.0 / 2.,
+                              lines.len() as f32 * line_height + 2. * padding);
 
-                self.sprites_ui = sprites.iter()
-                    .filter(|s| s.layer == Layer::UI)
-                    .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures))
-                    .collect::<Vec<(Image, DrawParam)>>();
+        let backdrop = Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0., 0., 0., 0.6))?;
+        canvas.draw(&backdrop, Vec2::new(0., 0.));
+        self.draw_panel(canvas, 0, panel);
 
-                self.sprites = sprites
-            }
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(canvas,
+                      &self.ui_text(line.as_str()),
+                      Vec2::new(panel.x + padding, panel.y + padding + i as f32 * line_height),
+                      graphics::DrawParam::new()
+                          .color(Theme::color(self.theme.text_color))
+                          .scale(Vec2::splat(self.text_scale())),
+                      self.theme.text_shadow());
         }
+        Ok(())
+    }
 
-        if let Some(state) = self.gameplay_state.clone() {
-            match state {
-                Actions::OPEN => {}
-                Actions::ATTACK => self.wait_for_attack(),
-                Actions::WALK_TO => {}
-                Actions::WATCH => self.wait_for_watch(),
-                Actions::USE => {}
-                Actions::EQUIP => {}
+    pub fn set_dim_behind_modals(&mut self, dim_behind_modals: bool) {
+        self.dim_behind_modals = dim_behind_modals;
+    }
+
+    fn is_dimming_behind_modals(&self) -> bool {
+        !self.modals.is_empty() && self.dim_behind_modals
+    }
+
+    fn draw_modal_backdrop(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let (width, height) = ctx.gfx.drawable_size();
+        let backdrop = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., width, height), Theme::color(self.theme.modal_backdrop_color))?;
+        canvas.draw(&backdrop, Vec2::new(0.0, 0.0));
+        Ok(())
+    }
+
+    pub fn set_emit_frame_ticks(&mut self, emit_frame_ticks: bool) {
+        self.emit_frame_ticks = emit_frame_ticks;
+    }
+
+    //Lets several windows share one bus by namespacing their topics, e.g. "p1/"
+    pub fn set_topic_prefix(&mut self, prefix: &str) {
+        self.router.set_prefix(prefix);
+    }
+
+    pub fn enable_ack(&mut self, topic: Topic) {
+        self.ack_topics.insert(topic);
+    }
+
+    //The gameplay sets the version of an ack-enabled message in its request id, the ack echoes it
+    fn acknowledge(&self, topic: Topic, version: Option<u64>) {
+        match version {
+            Some(version) if self.ack_topics.contains(&topic) => {
+                self.router.send(Topic::ACK, &(topic.name(), version));
             }
+            _ => ()
+        }
+    }
+
+    //Elapsed is the time since the start of the game in seconds
+    fn tick_frame(&mut self, elapsed: f64) {
+        self.frame_count += 1;
+        if self.emit_frame_ticks {
+            self.router.send(Topic::FRAME_TICK, &(self.frame_count, elapsed));
         }
+    }
 
-        self.mouse.set_pointer_position(point2.x, point2.y);
-        self.animator.advance(1., ctx.time.delta().as_secs_f64());
+    fn set_available_actions(&mut self) {
+        if let Some(actions) = self.router.try_recv::<Vec<u8>>(Topic::AVAILABLE_ACTIONS) {
+            self.idle.mark_active();
+            self.available_actions = actions.iter()
+                .filter_map(|a| Actions::from_u8(*a))
+                .collect();
+        }
+    }
 
-        self.particles.retain(|p: &(Image,DrawParam, Instant, u8)|  p.2.elapsed() < Duration::new(self.animation_duration as u64,0));
+    //The bar is only shown when the selected unit has something to do
+    fn draw_action_bar(&mut self, ctx: &Context, canvas: &mut Canvas) {
+        self.action_bar_buttons.clear();
+        if self.available_actions.is_empty() {
+            return;
+        }
 
-        Ok(())
+        let (_, height) = ctx.gfx.drawable_size();
+        let y = height - 34.;
+        for (i, action) in self.available_actions.clone().iter().enumerate() {
+            let button = Rect::new(10. + i as f32 * 110., y, 100., 24.);
+            self.draw_panel(canvas, 0, button);
+            canvas.draw(&self.ui_text(action.to_string()),
+                        graphics::DrawParam::from([button.x + 8., button.y + 5.])
+                            .color(Theme::color(self.theme.text_color))
+                            .scale(Vec2::splat(self.text_scale())));
+            self.action_bar_buttons.push(button);
+        }
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let fps = ctx.time.fps();
-        ctx.gfx.set_window_title(format!("fps: {0:.0}", fps).as_str());
-        let mut canvas = Canvas::from_frame(
-            ctx,
-            graphics::Color::from([0., 0., 0., 1.0]),
-        );
+    //Closest off-screen important sprites first
+    fn edge_markers(&self) -> Vec<EdgeMarker> {
+        let mut markers = self.sprites.iter()
+            .filter(|s| s.important)
+            .filter_map(|s| {
+                let center = self.world_to_screen(self.sprite_world_position(s) + Vec2::splat(self.tile_size as f32 / 2.));
+                EdgeMarker::from_target(self.viewport, center)
+            })
+            .collect::<Vec<EdgeMarker>>();
+        markers.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        markers.truncate(MAX_EDGE_MARKERS);
+        markers
+    }
+
+    pub fn set_flash(&mut self, flash_duration: Duration, shake_on_flash: bool) {
+        self.flash_duration = flash_duration;
+        self.shake_on_flash = shake_on_flash;
+    }
+
+    fn handle_combat_event(&mut self, event: &CombatEvent) {
+        let color = match event.kind {
+            CombatEventKind::CRITICAL => Some(Theme::color(self.theme.critical_flash_color)),
+            CombatEventKind::STATUS => Some(Theme::color(self.theme.status_flash_color)),
+            _ => None
+        };
 
-        for mesh in &self.sprites_background {
-            canvas.draw(&mesh.0, mesh.1);
+        //Only creatures with an id can be followed, the floating text is shown anyway
+        let target_id = self.sprites.iter()
+            .filter(|s| s.layer == Layer::MOVABLES)
+            .find(|s| (s.pos_x, s.pos_y) == (event.target.0 as i32, event.target.1 as i32))
+            .and_then(|s| s.id);
+        if let (Some(color), Some(id)) = (color, target_id) {
+            self.flashes.insert(id, (Instant::now(), color));
         }
-        for mesh in &self.sprites_movables {
-            canvas.draw(&mesh.0, mesh.1);
+
+        let floating_text = FloatingText::from_combat_event(event, self.tile_to_world(event.target));
+        self.push_floating_text(floating_text);
+    }
+
+    fn set_combat_events(&mut self) {
+        if let Some(event) = self.router.try_recv::<CombatEvent>(Topic::COMBAT_EVENT) {
+            self.idle.mark_active();
+            self.handle_combat_event(&event);
         }
-        for particle in &self.particles {
-            let mut local_clone = particle.clone();
-            canvas.draw(&particle.0, local_clone.1
-                .src(self.animator.get_currenct_rect(local_clone.3 as usize)));
+    }
+
+    fn tile_center(&self, tile: (u16, u16)) -> Vec2 {
+        self.grid_kind.tile_center((tile.0 as i32, tile.1 as i32), self.tile_size as f32)
+    }
+
+    fn set_pings(&mut self) {
+        if let Some(tile) = self.router.try_recv::<(u16, u16)>(Topic::PING) {
+            self.idle.mark_active();
+            self.push_ping(Ping::new(self.tile_center(tile)));
         }
-        for mesh in &self.sprites_ui {
-            canvas.draw(&mesh.0, mesh.1);
+    }
+
+    //Shooter tile then target tile
+    fn set_projectiles(&mut self) {
+        if let Some((from, to)) = self.router.try_recv::<((u16, u16), (u16, u16))>(Topic::PROJECTILE) {
+            self.idle.mark_active();
+            self.push_projectile(Projectile::new(self.tile_center(from), self.tile_center(to)));
         }
+    }
 
-        if self.current_menu.len() > 0 {
-            let options = self.current_menu.clone();
-            self.draw_menu(&mut canvas, 0., 200.0, options)?;
+    //Goes from 1 when the flash starts down to 0 when it expires
+    fn flash_strength(&self, id: i64) -> f32 {
+        if let Some((start, _)) = self.flashes.get(&id) {
+            (1. - start.elapsed().as_secs_f32() / self.flash_duration.as_secs_f32()).max(0.)
+        } else {
+            0.
         }
+    }
 
-        canvas.draw(&Text::new(self.stdout.clone()),
-                    graphics::DrawParam::from(Vec2::new(200.0, 0.0)).color(Color::WHITE).scale(Vec2::new(1., 1.)));
+    fn expire_flashes(&mut self) {
+        let flash_duration = self.flash_duration;
+        self.flashes.retain(|_, (start, _)| start.elapsed() < flash_duration);
+    }
 
-        if let Some((x, y, content)) = self.active_modal.clone() {
-            self.draw_modal(&mut canvas, x, y, content.as_str())?;
+    fn shake_offset(&self, sprite: &Sprite) -> Vec2 {
+        let strength = sprite.id.map_or(0., |id| self.flash_strength(id));
+        if !self.shake_on_flash || strength <= 0. {
+            return Vec2::ZERO;
         }
 
-        canvas.draw(&self.mouse.get_mesh(&ctx), Vec2::new(0.0, 0.0));
+        self.shake_jitter * SHAKE_AMPLITUDE * strength
+    }
 
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = XorShift::new(seed);
+    }
 
-        canvas.finish(ctx)?;
-        Ok(())
+    //Drawn once per frame so every shaking sprite moves the same way
+    fn update_shake_jitter(&mut self) {
+        self.shake_jitter = Vec2::new(self.rng.next_f32() * 2. - 1., self.rng.next_f32() * 2. - 1.);
     }
-}
 
-pub fn init(receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) -> GameResult {
-    let cb = ggez::ContextBuilder::new("super simple", "ggez")
-        .window_mode(WindowMode::default().dimensions(800.0, 600.0))
-        .window_setup(WindowSetup::default().samples(NumSamples::Four));
-    let (mut ctx, event_loop) = cb.build()?;
+    pub fn set_blink_low_life_player(&mut self, blink_low_life_player: bool) {
+        self.blink_low_life_player = blink_low_life_player;
+    }
 
+    fn is_low_life(&self, sprite: &Sprite) -> bool {
+        if sprite.important && !self.blink_low_life_player {
+            return false;
+        }
 
-    let state = MainState::new(&ctx, receivers, senders)?;
-    event::run(ctx, event_loop, state)
-}
\ No newline at end of file
+        match sprite.life_ratio() {
+            Some(ratio) => ratio < LOW_LIFE_THRESHOLD,
+            None => false
+        }
+    }
+
+    //The lower the life, the faster the sprite pulses
+    fn low_life_alpha(&self, sprite: &Sprite, time: f32) -> f32 {
+        if !self.is_low_life(sprite) {
+            return 1.;
+        }
+
+        let ratio = sprite.life_ratio().unwrap() / LOW_LIFE_THRESHOLD;
+        let rate = LOW_LIFE_MAX_BLINK_RATE - (LOW_LIFE_MAX_BLINK_RATE - LOW_LIFE_MIN_BLINK_RATE) * ratio;
+        0.3 + 0.7 * (0.5 + 0.5 * (time * rate * std::f32::consts::TAU).cos())
+    }
+
+    //One mesh for all the bars, drawn right above each visible creature
+    fn draw_health_bars(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<bool> {
+        let tile_size = self.tile_size as f32;
+        let mut builder = MeshBuilder::new();
+        let mut has_bars = false;
+        for sprite in self.sprites.iter().filter(|s| s.layer == Layer::MOVABLES && self.is_on_screen(s)) {
+            if let Some((life, max_life)) = sprite.life {
+                let origin = self.movable_position(sprite)
+                    - Vec2::new(0., HEALTH_BAR_HEIGHT + HEALTH_BAR_MARGIN);
+                builder.rectangle(DrawMode::fill(),
+                                  Rect::new(origin.x, origin.y, tile_size, HEALTH_BAR_HEIGHT),
+                                  Color::RED)?;
+                let width = Sprite::health_bar_width(life, max_life, tile_size);
+                if width > 0. {
+                    builder.rectangle(DrawMode::fill(),
+                                      Rect::new(origin.x, origin.y, width, HEALTH_BAR_HEIGHT),
+                                      Color::GREEN)?;
+                }
+                has_bars = true;
+            }
+        }
+
+        if has_bars {
+            canvas.draw(&Mesh::from_data(ctx, builder.build()), Self::with_layer_alpha(DrawParam::new(), self.movables_alpha));
+        }
+        Ok(has_bars)
+    }
+
+    //World position of the top left corner of the tile under a screen point
+    pub fn snap_to_tile(&self, x: f32, y: f32) -> Option<Vec2> {
+        self.screen_to_tile(x, y).map(|tile| self.tile_to_world(tile))
+    }
+
+    pub fn toggle_grid(&mut self) {
+        self.show_grid = !self.show_grid;
+    }
+
+    //Drawn over the visible part of the world, square grids as lines and hex grids as tile outlines
+    fn draw_grid(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<bool> {
+        if !self.show_grid {
+            return Ok(false);
+        }
+
+        let tile_size = self.tile_size as f32;
+        let (width, height) = self.visible_world_size(self.viewport);
+        let color = Color::new(1., 1., 1., 0.15);
+        let mut builder = MeshBuilder::new();
+        if self.grid_kind != GridKind::SQUARE {
+            //One more tile around the view covers the hexes partly visible on the edges
+            let first = self.grid_kind.world_to_tile(self.camera, tile_size);
+            let last = self.grid_kind.world_to_tile(self.camera + Vec2::new(width, height), tile_size);
+            for x in first.0 - 1..=last.0 + 1 {
+                for y in first.1 - 1..=last.1 + 1 {
+                    builder.polygon(DrawMode::stroke(1.), &self.grid_kind.tile_corners((x, y), tile_size), color)?;
+                }
+            }
+            canvas.draw(&Mesh::from_data(ctx, builder.build()), DrawParam::new());
+            return Ok(true);
+        }
+
+        let mut x = first_grid_line(self.camera.x, tile_size);
+        while x <= self.camera.x + width {
+            builder.line(&[Vec2::new(x, self.camera.y), Vec2::new(x, self.camera.y + height)], 1., color)?;
+            x += tile_size;
+        }
+        let mut y = first_grid_line(self.camera.y, tile_size);
+        while y <= self.camera.y + height {
+            builder.line(&[Vec2::new(self.camera.x, y), Vec2::new(self.camera.x + width, y)], 1., color)?;
+            y += tile_size;
+        }
+
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), DrawParam::new());
+        Ok(true)
+    }
+
+    fn draw_selection_outline(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<bool> {
+        if let Some(tile) = self.selection {
+            let position = self.tile_to_world(tile);
+            let outline = Mesh::new_rectangle(ctx,
+                                              DrawMode::stroke(self.outline_thickness()),
+                                              Rect::new(position.x, position.y, self.tile_size as f32, self.tile_size as f32),
+                                              Theme::color(self.theme.highlight_color))?;
+            canvas.draw(&outline, Vec2::new(0., 0.));
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn draw_hover_highlight(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<bool> {
+        if let Some(position) = self.snap_to_tile(self.mouse.pos_x, self.mouse.pos_y) {
+            let highlight = Mesh::new_rectangle(ctx,
+                                                DrawMode::stroke(self.outline_thickness()),
+                                                Rect::new(position.x, position.y, self.tile_size as f32, self.tile_size as f32),
+                                                Color::new(1., 1., 1., 0.5))?;
+            canvas.draw(&highlight, Vec2::new(0., 0.));
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn draw_flashes(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        for (id, (_, color)) in &self.flashes {
+            let sprite = match self.sprites.iter().find(|s| s.id == Some(*id)) {
+                Some(sprite) => sprite,
+                None => continue
+            };
+            let strength = self.flash_strength(*id);
+            let position = self.movable_position(sprite);
+            let flash = Mesh::new_rectangle(ctx,
+                                            DrawMode::fill(),
+                                            Rect::new(position.x, position.y, self.tile_size as f32, self.tile_size as f32),
+                                            Color::new(color.r, color.g, color.b, 0.7 * strength))?;
+            canvas.draw(&flash, Vec2::new(0., 0.));
+        }
+        Ok(())
+    }
+
+    pub fn set_show_turn_timer(&mut self, show_turn_timer: bool) {
+        self.show_turn_timer = show_turn_timer;
+    }
+
+    fn set_turn_timer(&mut self) {
+        if let Some(remaining) = self.router.try_recv::<f32>(Topic::TURN_TIMER) {
+            self.idle.mark_active();
+            self.turn_timer = if remaining > 0. { Some((remaining, remaining)) } else { None };
+        }
+    }
+
+    //The timeout is only sent once, the timer is dropped when it fires
+    fn update_turn_timer(&mut self, delta: f32) {
+        if let Some((remaining, total)) = self.turn_timer {
+            let remaining = remaining - delta;
+            if remaining > 0. {
+                self.turn_timer = Some((remaining, total));
+                return;
+            }
+
+            self.turn_timer = None;
+            self.router.signal(Topic::TURN_TIMEOUT);
+        }
+    }
+
+    fn draw_turn_timer(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if !self.show_turn_timer {
+            return Ok(());
+        }
+
+        if let Some((remaining, total)) = self.turn_timer {
+            let origin = Vec2::new((self.viewport.0 - TURN_TIMER_SIZE.x) / 2., self.theme.panel_padding);
+            //Blinks twice per second once the warning threshold is reached
+            let color = if remaining <= TURN_TIMER_WARNING && (remaining * 4.) as u32 % 2 == 0 {
+                Theme::color(self.theme.turn_timer_warning_color)
+            } else {
+                Theme::color(self.theme.turn_timer_color)
+            };
+
+            let bar = Mesh::new_rectangle(ctx,
+                                          DrawMode::fill(),
+                                          Rect::new(origin.x, origin.y, TURN_TIMER_SIZE.x * remaining / total, TURN_TIMER_SIZE.y),
+                                          color)?;
+            canvas.draw(&bar, Vec2::new(0., 0.));
+            canvas.draw(&self.ui_text(format!("{:.0}", remaining.ceil())),
+                        DrawParam::new()
+                            .dest(origin + Vec2::new(TURN_TIMER_SIZE.x + 6., -4.))
+                            .color(color));
+        }
+        Ok(())
+    }
+
+    //Payload is the tile the attack came from
+    fn set_damage_source(&mut self) {
+        if let Some(source) = self.router.try_recv::<(u16, u16)>(Topic::PLAYER_HIT) {
+            self.idle.mark_active();
+            self.damage_source = Some((source, Instant::now()));
+        }
+    }
+
+    fn damage_source_strength(&self) -> f32 {
+        match self.damage_source {
+            Some((_, start)) => (1. - start.elapsed().as_secs_f32() / DAMAGE_SOURCE_DURATION.as_secs_f32()).max(0.),
+            None => 0.
+        }
+    }
+
+    fn draw_damage_source(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if let Some((tile, _)) = self.damage_source {
+            let color = Color::new(1., 0., 0., self.damage_source_strength());
+            let position = self.tile_to_world(tile);
+            let highlight = Mesh::new_rectangle(ctx,
+                                                DrawMode::stroke(self.outline_thickness()),
+                                                Rect::new(position.x, position.y, self.tile_size as f32, self.tile_size as f32),
+                                                color)?;
+            canvas.draw(&highlight, Vec2::new(0., 0.));
+        }
+        Ok(())
+    }
+
+    //Drawn in screen coordinates, only when the source is out of the viewport
+    fn draw_damage_source_marker(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if let Some((tile, _)) = self.damage_source {
+            let center = self.world_to_screen(self.tile_to_world(tile) + Vec2::splat(self.tile_size as f32 / 2.));
+            if let Some(marker) = EdgeMarker::from_target(self.viewport, center) {
+                marker.draw(ctx, canvas, self.tile_size as f32, Color::new(1., 0., 0., self.damage_source_strength()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_weather(&mut self) {
+        if let Some((kind, intensity)) = self.router.try_recv::<(WeatherKind, f32)>(Topic::WEATHER) {
+            self.idle.mark_active();
+            self.weather.set(kind, intensity);
+        }
+    }
+
+    fn send_action(&self, action: &Actions) {
+        self.router.send(Topic::ACTION, &action.to_u8());
+    }
+
+    fn mouse_hovering_characterisation(&mut self, x: f32, y: f32, sprites: Vec<Sprite>) {
+        if let Some(gameplay_state) = self.gameplay_state.clone() {
+            match gameplay_state {
+                Actions::WATCH => self.watch_action(&x, &y, sprites),
+                Actions::ATTACK => self.attack_action(&x, &y, sprites),
+                Actions::MOVE => self.move_action(&x, &y),
+                _ => ()
+            }
+        }
+    }
+
+    //Single source of truth for the selected tile, the other systems follow the selection_changed topic
+    pub fn set_selection(&mut self, selection: Option<(u16, u16)>) {
+        if selection == self.selection {
+            return;
+        }
+
+        self.selection = selection;
+        self.router.send(Topic::SELECTION_CHANGED, &selection);
+    }
+
+    //Creatures are cycled in reading order, starting over after the last one
+    fn select_next_creature(&mut self) {
+        let mut tiles = self.sprites.iter()
+            .filter(|s| s.layer == Layer::MOVABLES)
+            .filter_map(|s| s.tile())
+            .collect::<Vec<(u16, u16)>>();
+        tiles.sort_by_key(|tile| (tile.1, tile.0));
+        tiles.dedup();
+
+        let next = match self.selection.and_then(|selected| tiles.iter().position(|tile| *tile == selected)) {
+            Some(index) => tiles.get((index + 1) % tiles.len()),
+            None => tiles.first()
+        };
+        if let Some(tile) = next.copied() {
+            self.set_selection(Some(tile));
+        }
+    }
+
+    fn set_gameplay_state(&mut self) {
+        if let Some(state) = self.router.try_recv::<u8>(Topic::GAMEPLAY_STATE) {
+            self.gameplay_state = Actions::from_u8(state);
+        }
+    }
+
+    fn attack_action(&mut self, x: &f32, y: &f32, sprites: Vec<Sprite>) {
+        //Send click position info
+        self.send_info_message(&x, &y);
+    }
+
+    //Nothing is sent when a creature already stands on the tile
+    fn move_action(&mut self, x: &f32, y: &f32) {
+        if let Some(tile) = self.screen_to_tile(*x, *y) {
+            let occupied = self.sprites.iter()
+                .any(|s| s.layer == Layer::MOVABLES && s.tile() == Some(tile));
+            if occupied {
+                return;
+            }
+
+            self.router.send(Topic::MOVE, &tile);
+            self.clear_after_turn();
+        }
+    }
+
+    //Clicks outside of the board are not sent, their tile would not exist on the gameplay side
+    fn send_info_message(&mut self, x: &f32, y: &f32) -> bool {
+        match self.screen_to_tile(*x, *y) {
+            Some(tile) => {
+                self.info_request_id += 1;
+                self.router.send_request(Topic::INFO, &tile, self.info_request_id)
+            }
+            None => false
+        }
+    }
+
+    //An answer to an older request would describe a tile that is not hovered anymore
+    fn is_current_info_response(&self, response: &MessageContent) -> bool {
+        response.request_id == Some(self.info_request_id)
+    }
+
+    //Board size in tiles, deduced from the background layer
+    fn board_size(&self) -> (u16, u16) {
+        self.sprites.iter()
+            .filter(|s| s.layer == Layer::BACKGROUND)
+            .filter_map(|s| s.tile())
+            .fold((0, 0), |(w, h), (x, y)| (w.max(x.saturating_add(1)), h.max(y.saturating_add(1))))
+    }
+
+    pub fn set_grid_kind(&mut self, grid_kind: GridKind) {
+        self.grid_kind = grid_kind;
+        let sprites = self.sprites.clone();
+        self.set_sprites(sprites);
+    }
+
+    pub fn tile_to_world(&self, tile: (u16, u16)) -> Vec2 {
+        self.grid_kind.tile_to_world((tile.0 as i32, tile.1 as i32), self.tile_size as f32)
+    }
+
+    pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+        (world - self.camera) * self.zoom
+    }
+
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        screen / self.zoom + self.camera
+    }
+
+    //Size of the part of the world the viewport shows at the current zoom
+    fn visible_world_size(&self, viewport: (f32, f32)) -> (f32, f32) {
+        (viewport.0 / self.zoom, viewport.1 / self.zoom)
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn clamp_zoom(zoom: f32) -> f32 {
+        zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+
+    //Camera keeping the world point under the cursor in place once the zoom changes
+    pub fn zoom_anchored_camera(camera: Vec2, cursor: Vec2, old_zoom: f32, new_zoom: f32) -> Vec2 {
+        camera + cursor / old_zoom - cursor / new_zoom
+    }
+
+    pub fn zoom_at(&mut self, x: f32, y: f32, zoom: f32) {
+        let zoom = Self::clamp_zoom(zoom);
+        if zoom == self.zoom {
+            return;
+        }
+
+        self.camera = Self::zoom_anchored_camera(self.camera, Vec2::new(x, y), self.zoom, zoom);
+        self.zoom = zoom;
+        self.clamp_camera(self.viewport);
+        self.camera_target = self.camera;
+    }
+
+    //Each notch zooms by the same factor, so zooming in then out gets back to the same scale
+    fn handle_mouse_wheel(&mut self, x: f32, y: f32, notches: f32) {
+        self.zoom_at(x, y, self.zoom * ZOOM_STEP.powf(notches));
+    }
+
+    //Same as tile_to_world, for sprites whose position may be off the board
+    fn sprite_world_position(&self, sprite: &Sprite) -> Vec2 {
+        self.grid_kind.tile_to_world((sprite.pos_x, sprite.pos_y), self.tile_size as f32)
+    }
+
+    pub fn tile_to_screen(&self, tile: (u16, u16)) -> Vec2 {
+        self.world_to_screen(self.tile_to_world(tile))
+    }
+
+    fn start_drag(&mut self, button: MouseButton, x: f32, y: f32) {
+        self.drag = Some((Vec2::new(x, y), Instant::now(), button));
+        self.drag_velocity = Vec2::ZERO;
+        self.camera_velocity = Vec2::ZERO;
+    }
+
+    fn drag_to(&mut self, x: f32, y: f32, time: Instant) {
+        if let Some((last_position, last_time, button)) = self.drag {
+            let position = Vec2::new(x, y);
+            let elapsed = time.saturating_duration_since(last_time).as_secs_f32();
+            let delta = (last_position - position) / self.zoom;
+
+            //Dragging cannot take the view past the edges of the map
+            self.camera = self.clamp_to_world(self.camera + delta, self.viewport);
+            if elapsed > 0. {
+                self.drag_velocity = delta / elapsed;
+            }
+            self.drag = Some((position, time.max(last_time), button));
+        }
+    }
+
+    fn end_drag(&mut self) {
+        self.camera_velocity = self.drag_velocity;
+        self.drag = None;
+    }
+
+    pub fn set_camera_follow(&mut self, camera_follow: bool, smoothing: f32, dead_zone: Vec2) {
+        self.camera_follow = camera_follow;
+        self.camera_smoothing = smoothing;
+        self.camera_dead_zone = dead_zone;
+        self.camera_target = self.camera;
+    }
+
+    //The target only moves when the followed sprite leaves the dead zone around the viewport center
+    //The first important creature is followed, whatever its texture
+    fn update_camera_target(&mut self, viewport: (f32, f32)) {
+        let followed = self.sprites.iter()
+            .find(|s| s.important && s.layer == Layer::MOVABLES);
+
+        if let Some(sprite) = followed {
+            let sprite_center = self.sprite_world_position(sprite) + Vec2::splat(self.tile_size as f32 / 2.);
+            let (width, height) = self.visible_world_size(viewport);
+            let offset = sprite_center - (self.camera_target + Vec2::new(width, height) / 2.);
+
+            if offset.x.abs() > self.camera_dead_zone.x {
+                self.camera_target.x += offset.x - offset.x.signum() * self.camera_dead_zone.x;
+            }
+            if offset.y.abs() > self.camera_dead_zone.y {
+                self.camera_target.y += offset.y - offset.y.signum() * self.camera_dead_zone.y;
+            }
+            //The target stays reachable, otherwise the camera would keep pushing against the map bounds
+            self.camera_target = self.clamp_to_world(self.camera_target, viewport);
+        }
+    }
+
+    fn follow_camera(&mut self, delta: f32, viewport: (f32, f32)) {
+        if !self.camera_follow || self.drag.is_some() {
+            return;
+        }
+
+        self.update_camera_target(viewport);
+        self.camera += (self.camera_target - self.camera) * (self.camera_smoothing * delta).min(1.);
+        self.clamp_camera(viewport);
+    }
+
+    fn apply_camera_momentum(&mut self, delta: f32, viewport: (f32, f32)) {
+        if self.drag.is_none() && self.camera_velocity.length() > MIN_CAMERA_SPEED {
+            self.camera += self.camera_velocity * delta;
+            self.camera_velocity *= (1. - CAMERA_FRICTION * delta).max(0.);
+        } else if self.drag.is_none() {
+            self.camera_velocity = Vec2::ZERO;
+        }
+        self.clamp_camera(viewport);
+    }
+
+    fn world_size(&self) -> Vec2 {
+        self.grid_kind.world_size(self.board_size(), self.tile_size as f32)
+    }
+
+    pub fn set_minimap_recenter_on_click(&mut self, recenter_on_click: bool) {
+        self.minimap.recenter_on_click = recenter_on_click;
+    }
+
+    fn center_camera_on_minimap(&mut self, x: f32, y: f32) {
+        let world = self.minimap.minimap_to_world(self.viewport, Vec2::new(x, y), self.world_size());
+        let (width, height) = self.visible_world_size(self.viewport);
+        self.camera = world - Vec2::new(width, height) / 2.;
+        self.clamp_camera(self.viewport);
+        self.camera_target = self.camera;
+    }
+
+    //Movables are drawn after the background so creatures stay visible on their tile
+    fn minimap_dots(&self) -> Vec<(Vec2, Color)> {
+        let background = self.sprites.iter()
+            .filter(|s| s.layer == Layer::BACKGROUND)
+            .map(|s| (s, Theme::color(self.theme.minimap_background_dot_color)));
+        let movables = self.sprites.iter()
+            .filter(|s| s.layer == Layer::MOVABLES)
+            .map(|s| (s, Theme::color(self.theme.minimap_movable_dot_color)));
+        background.chain(movables)
+            .filter(|(s, _)| s.pos_x >= 0 && s.pos_y >= 0)
+            .map(|(s, color)| (self.sprite_world_position(s), color))
+            .collect()
+    }
+
+    fn drag_minimap_to(&mut self, x: f32, y: f32) {
+        if let Some(last_position) = self.minimap_drag {
+            let position = Vec2::new(x, y);
+            self.camera += self.minimap.minimap_delta_to_world(position - last_position, self.world_size());
+            self.camera_target = self.camera;
+            self.clamp_camera(self.viewport);
+            self.minimap_drag = Some(position);
+        }
+    }
+
+    fn clamp_to_world(&self, position: Vec2, viewport: (f32, f32)) -> Vec2 {
+        let world_size = self.world_size();
+        let (width, height) = self.visible_world_size(viewport);
+        let max_x = (world_size.x - width).max(0.);
+        let max_y = (world_size.y - height).max(0.);
+        Vec2::new(position.x.clamp(0., max_x), position.y.clamp(0., max_y))
+    }
+
+    fn clamp_camera(&mut self, viewport: (f32, f32)) {
+        self.camera = self.clamp_to_world(self.camera, viewport);
+    }
+
+    //Every outline (selection, faction, status) should use this so they stay readable at any zoom
+    //Outlines are drawn in world pixels, the canvas scales them back by the zoom
+    pub fn outline_thickness(&self) -> f32 {
+        (self.theme.outline_thickness * self.zoom).clamp(self.theme.min_outline_thickness, self.theme.max_outline_thickness) / self.zoom
+    }
+
+    pub fn set_layer_alpha(&mut self, layer: Layer, alpha: f32) {
+        match layer {
+            Layer::BACKGROUND => {
+                self.background_alpha = alpha;
+                self.background_dirty = true;
+            }
+            Layer::MOVABLES => self.movables_alpha = alpha,
+            Layer::UI => self.ui_alpha = alpha,
+            Layer::PARTICLE => ()
+        }
+    }
+
+    pub fn interpolate_position(from: Vec2, to: Vec2, progress: f32) -> Vec2 {
+        from + (to - from) * progress.clamp(0., 1.)
+    }
+
+    //Where a creature is drawn, on its way to its tile when it just moved
+    fn movable_position(&self, sprite: &Sprite) -> Vec2 {
+        self.slide_position(sprite) + self.shake_offset(sprite)
+    }
+
+    fn slide_position(&self, sprite: &Sprite) -> Vec2 {
+        let target = self.sprite_world_position(sprite);
+        match sprite.id.and_then(|id| self.movements.get(&id)) {
+            Some((from, start)) => Self::interpolate_position(*from, target, start.elapsed().as_secs_f32() / MOVE_DURATION.as_secs_f32()),
+            None => target
+        }
+    }
+
+    //Layer alpha multiplies the alpha the sprite already carries
+    fn with_layer_alpha(param: DrawParam, alpha: f32) -> DrawParam {
+        let mut param = param;
+        param.color.a *= alpha;
+        param
+    }
+
+    fn sprite_drawable(&self, sprite: &Sprite) -> (u16, DrawParam) {
+        sprite.create_drawable(self.sprite_world_position(sprite))
+    }
+
+    pub fn void_color(&self) -> Color {
+        Theme::color(self.theme.void_color)
+    }
+
+    //Sprites referencing a texture that was never loaded are not drawn
+    fn texture(&self, texture_id: u16) -> Option<&Image> {
+        self.sprites_textures.get(&texture_id)
+    }
+
+    //Sprites shapes only apply to square tiles, on hexes the whole cell is the hit area
+    fn sprite_contains(&self, sprite: &Sprite, world_x: f32, world_y: f32) -> bool {
+        match self.grid_kind {
+            GridKind::SQUARE => sprite.contains(world_x, world_y, self.tile_size as f32),
+            _ => self.grid_kind.world_to_tile(Vec2::new(world_x, world_y), self.tile_size as f32) == (sprite.pos_x, sprite.pos_y)
+        }
+    }
+
+    //Every sprite under a screen pixel, in draw order, shared by clicks, hover and the context menu
+    pub fn sprites_at_pixel(&self, x: f32, y: f32) -> Vec<Sprite> {
+        let world = self.screen_to_world(Vec2::new(x, y));
+        self.sprites.iter()
+            .filter(|s| self.sprite_contains(s, world.x, world.y))
+            .cloned()
+            .collect::<Vec<Sprite>>()
+    }
+
+    pub fn render_stats(&self) -> &RenderStats {
+        &self.render_stats
+    }
+
+    pub fn set_ui_scale(&mut self, ui_scale: f32) {
+        self.ui_scale = ui_scale.max(0.1);
+    }
+
+    pub fn set_font(&mut self, font: Option<&str>) {
+        self.font = font.map(|f| f.to_string());
+    }
+
+    fn text_scale(&self) -> f32 {
+        self.theme.text_scale * self.ui_scale
+    }
+
+    fn ui_text(&self, content: impl Into<TextFragment>) -> Text {
+        let mut text = Text::new(content);
+        if let Some(font) = &self.font {
+            text.set_font(font.as_str());
+        }
+        text
+    }
+
+    //The oldest toast goes away when too many failures pile up
+    pub fn push_toast(&mut self, message: String) {
+        self.toasts.push((Instant::now(), message));
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    fn surface_router_errors(&mut self) {
+        for error in self.router.take_errors() {
+            self.push_toast(error);
+        }
+    }
+
+    fn prune_toasts(&mut self, now: Instant) {
+        self.toasts.retain(|(start, _)| now.duration_since(*start) < TOAST_DURATION);
+    }
+
+    //Newest at the bottom of the bottom right corner
+    fn draw_toasts(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<bool> {
+        if self.toasts.is_empty() {
+            return Ok(false);
+        }
+
+        let padding = self.theme.panel_padding;
+        let line_height = self.theme.menu_line_height * self.text_scale();
+        let mut builder = MeshBuilder::new();
+        let mut texts = vec![];
+        for (i, (_, message)) in self.toasts.iter().rev().enumerate() {
+            let text = self.ui_text(message.as_str());
+            let width = text.dimensions(ctx).map_or(0., |r| r.w) * self.text_scale();
+            let origin = Vec2::new(self.viewport.0 - width - 2. * padding,
+                                   self.viewport.1 - (i + 1) as f32 * (line_height + padding));
+            builder.rectangle(DrawMode::fill(),
+                              Rect::new(origin.x - padding / 2., origin.y - padding / 4., width + padding, line_height),
+                              Theme::color(self.theme.toast_color))?;
+            texts.push((text, origin));
+        }
+
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), DrawParam::new());
+        for (text, origin) in texts {
+            draw_text(canvas,
+                      &text,
+                      origin,
+                      graphics::DrawParam::new()
+                          .color(Color::WHITE)
+                          .scale(Vec2::splat(self.text_scale())),
+                      self.theme.text_shadow());
+        }
+        Ok(true)
+    }
+
+    pub fn current_fps(&self, ctx: &Context) -> f64 {
+        ctx.time.fps()
+    }
+
+    pub fn toggle_fps_overlay(&mut self) {
+        self.show_fps = !self.show_fps;
+    }
+
+    //Frame time is the one of the previous frame, the current one is still being drawn
+    fn draw_fps_overlay(&self, ctx: &Context, canvas: &mut Canvas) {
+        if !self.show_fps {
+            return;
+        }
+
+        draw_text(canvas,
+                  &self.ui_text(self.fps_overlay_text(self.current_fps(ctx))),
+                  Vec2::new(self.theme.panel_padding, self.viewport.1 - self.theme.panel_padding - self.theme.menu_line_height),
+                  graphics::DrawParam::new().color(Theme::color(self.theme.fps_color)),
+                  self.theme.text_shadow());
+        draw_text(canvas,
+                  &self.ui_text(self.effects_overlay_text()),
+                  Vec2::new(self.theme.panel_padding, self.viewport.1 - self.theme.panel_padding - self.theme.menu_line_height * 2.),
+                  graphics::DrawParam::new().color(Theme::color(self.theme.fps_color)),
+                  self.theme.text_shadow());
+    }
+
+    fn fps_overlay_text(&self, fps: f64) -> String {
+        format!("{:.0} fps {:.1} ms", fps, self.render_stats.frame_time.as_secs_f64() * 1000.)
+    }
+
+    fn effects_overlay_text(&self) -> String {
+        format!("effects {}/{} : {} particles {} texts {} pings {} projectiles",
+                self.active_effects_count(),
+                self.max_effects,
+                self.particles.len(),
+                self.floating_texts.len(),
+                self.pings.len(),
+                self.projectiles.len())
+    }
+
+    fn is_on_screen(&self, sprite: &Sprite) -> bool {
+        let position = self.world_to_screen(self.sprite_world_position(sprite));
+        let size = self.tile_size as f32 * self.zoom;
+        position.x + size > 0. && position.y + size > 0. &&
+            position.x < self.viewport.0 && position.y < self.viewport.1
+    }
+
+    //A minimized window reports a zero size, nothing can be mapped or drawn until it comes back
+    fn has_valid_viewport(&self) -> bool {
+        self.viewport.0 > 0. && self.viewport.1 > 0.
+    }
+
+    pub fn screen_to_tile(&self, x: f32, y: f32) -> Option<(u16, u16)> {
+        if !self.has_valid_viewport() {
+            return None;
+        }
+
+        let tile = self.grid_kind.world_to_tile(self.screen_to_world(Vec2::new(x, y)), self.tile_size as f32);
+        //Negative coordinates and the ones past u16 are off the board anyway
+        let tile = (u16::try_from(tile.0).ok()?, u16::try_from(tile.1).ok()?);
+        let (width, height) = self.board_size();
+        if tile.0 >= width || tile.1 >= height {
+            return None;
+        }
+
+        Some(tile)
+    }
+
+    //Info is requested once per tile, after the cursor rested on it long enough
+    fn update_hover(&mut self) {
+        let tile = self.screen_to_tile(self.mouse.pos_x, self.mouse.pos_y);
+        if tile != self.hover.map(|(hovered, _)| hovered) {
+            self.hover = tile.map(|t| (t, Instant::now()));
+            self.hover_requested = false;
+            if self.tooltip_open {
+                self.tooltip_open = false;
+                self.close_modal();
+            }
+            return;
+        }
+
+        if self.hover_requested || self.gameplay_state != Some(Actions::WATCH) {
+            return;
+        }
+
+        if self.hover.map_or(false, |(_, start)| start.elapsed() >= TOOLTIP_DELAY) {
+            let (x, y) = (self.mouse.pos_x, self.mouse.pos_y);
+            let sprites = self.sprites_at_pixel(x, y);
+
+            if !sprites.is_empty() {
+                log::debug!("Hovering tile {:?}", self.hover.map(|(tile, _)| tile));
+                self.hover_requested = true;
+                self.watch_action(&x, &y, sprites);
+            }
+        }
+    }
+
+    fn watch_action(&mut self, x: &f32, y: &f32, sprites: Vec<Sprite>) {
+        //Send click position info
+        if !self.send_info_message(&x, &y) {
+            return;
+        }
+        self.pending_info = Some(Instant::now());
+        self.sprites_clicked = sprites.iter()
+            .map(|s| (x.clone(), y.clone(), s.clone()))
+            .collect::<Vec<(f32, f32, Sprite)>>();
+    }
+
+    fn get_all_targetables_cell_to_sprites(&self) -> Vec<Sprite> {
+        //Get all targetables cells
+        let targetable_coordinates: Vec<Vec<bool>> = self.router.try_recv(Topic::TARGETABLE)
+            .unwrap_or_default();
+        Self::cells_to_sprites(&targetable_coordinates, 2)
+    }
+
+    fn cells_to_sprites(cells: &Vec<Vec<bool>>, texture_id: u16) -> Vec<Sprite> {
+        cells.iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter().enumerate()
+                    .filter(|(x, &cell)| cell)
+                    .map(|(x, &cell)|
+                        Sprite::new(texture_id, x as i32, y as i32, Layer::UI)
+                    )
+                    .collect::<Vec<Sprite>>()
+            })
+            .collect::<Vec<Sprite>>()
+    }
+
+    fn set_watchable_overlay(&mut self) {
+        if let Some(cells) = self.router.try_recv::<Vec<Vec<bool>>>(Topic::WATCHABLE) {
+            self.watchable_overlay = Self::cells_to_sprites(&cells, 2).iter()
+                .map(|s| self.sprite_drawable(s))
+                .map(|(image, param)| (image, Self::with_layer_alpha(param, WATCHABLE_OVERLAY_ALPHA)))
+                .collect::<Vec<(u16, DrawParam)>>();
+        }
+    }
+
+    //Payload is the center followed by the affected tiles
+    fn set_aoe_preview(&mut self) {
+        if let Some((center, tiles)) = self.router.try_recv::<((u16, u16), Vec<(u16, u16)>)>(Topic::AOE_PREVIEW) {
+            self.idle.mark_active();
+            self.aoe_preview = Some(AoePreview::new(center, &tiles));
+        }
+    }
+
+    fn update_aoe_preview(&mut self) {
+        if let Some(tile) = self.screen_to_tile(self.mouse.pos_x, self.mouse.pos_y) {
+            if let Some(preview) = self.aoe_preview.as_mut() {
+                preview.recenter(tile);
+            }
+        }
+    }
+
+    fn draw_aoe_preview(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if let Some(preview) = &self.aoe_preview {
+            for (tile, strength) in preview.tiles(self.board_size()) {
+                let position = self.tile_to_world(tile);
+                let area = Mesh::new_rectangle(ctx,
+                                               DrawMode::fill(),
+                                               Rect::new(position.x, position.y, self.tile_size as f32, self.tile_size as f32),
+                                               Color::new(1., 0.4, 0., 0.6 * strength))?;
+                canvas.draw(&area, Vec2::new(0., 0.));
+            }
+        }
+        Ok(())
+    }
+
+    fn wait_for_watch(&mut self) {
+        self.set_watchable_overlay();
+
+        let hovering_info =
+            if let Some(response) = self.router.try_recv_raw(Topic::INFO_RESPONSE) {
+                if self.is_current_info_response(&response) {
+                    self.router.decode_text(Topic::INFO_RESPONSE, response.content.as_slice())
+                } else {
+                    log::debug!("Dropping the answer to info request {:?}, waiting for {}", response.request_id, self.info_request_id);
+                    None
+                }
+            } else if self.pending_info.map_or(false, |sent| sent.elapsed() > self.info_timeout) {
+                //The gameplay never answered, we do not want to wait for it forever
+                Some("no info available".to_string())
+            } else {
+                None
+            };
+
+        if let Some(info) = hovering_info {
+            //Also after a timeout, the late answer is followed by the wait for the release
+            self.watch_awaiting_release = true;
+            let shown = self.sprites_clicked.is_empty().not() && !info.trim().is_empty();
+            if shown {
+                let first_element = self.sprites_clicked.first().unwrap().clone();
+                self.push_modal(first_element.0, first_element.1, info.to_string());
+            } else {
+                //The gameplay waits for the modal to be closed, with no modal we release it right away
+                self.release_watch();
+            }
+            self.tooltip_open = shown && self.hover_requested;
+
+            self.clear_after_turn();
+        }
+    }
+
+    fn clear_after_turn(&mut self) {
+        self.sprites_clicked.clear();
+        self.sprites_ui.clear();
+        self.watchable_overlay.clear();
+        self.aoe_preview = None;
+        self.set_selection(None);
+        self.pending_info = None;
+        self.gameplay_state = None;
+    }
+
+    fn wait_for_attack(&mut self) {
+        if let Some(response) = self.router.try_recv_raw(Topic::INFO_RESPONSE) {
+            if let Ok(ending_attack_turn) = from_utf8( response.content.as_slice()) {
+                if ending_attack_turn == "end_attack" {
+                    self.clear_after_turn();
+                    return;
+                }
+            }
+            if let Ok(target_position) = bincode::deserialize::<((u16, u16), DamageTypeEnum)>(response.content.as_slice()) {
+
+                let sprite = Sprite::new(1, target_position.0.0 as i32, target_position.0.1 as i32, Layer::UI);
+                let selector = self.sprite_drawable(&sprite);
+                self.sprites_ui.push(selector);
+                let attack_particle = self.sprite_drawable(&Sprite::new(100, target_position.0.0 as i32, target_position.0.1 as i32, Layer::PARTICLE));
+
+                let damage_type = match target_position.1 {
+                    DamageTypeEnum::SLASHING => 1,
+                    DamageTypeEnum::FIRE => 0,
+                    _ => 0
+                };
+
+                self.push_particle((attack_particle.0, attack_particle.1, Instant::now(), damage_type));
+            }
+        } else {
+            let mut targetable_cells = self.get_all_targetables_cell_to_sprites();
+            let mut targetable_drawables = targetable_cells.iter()
+                .filter(|s| s.layer == Layer::UI)
+                .map(|e| self.sprite_drawable(e))
+                .collect::<Vec<(u16, DrawParam)>>();
+            self.sprites_ui.append(&mut targetable_drawables);
+
+            self.sprites.append(&mut targetable_cells);
+        }
+    }
+}
+
+
+impl MainState {
+    //The gameplay answers on context_response with the actions available on the tile
+    fn request_context_menu(&mut self, x: f32, y: f32) {
+        if self.sprites_at_pixel(x, y).is_empty() {
+            return;
+        }
+
+        if let Some(tile) = self.screen_to_tile(x, y) {
+            if self.router.send(Topic::CONTEXT, &tile) {
+                self.context_menu_anchor = (x, y);
+            }
+        }
+    }
+
+    fn set_context_menu(&mut self) {
+        if let Some(actions) = self.router.try_recv::<Vec<u8>>(Topic::CONTEXT_RESPONSE) {
+            self.idle.mark_active();
+            let options = actions.iter()
+                .filter_map(|a| Actions::from_u8(*a))
+                .map(|a| a.to_string())
+                .collect();
+            self.replace_menus(self.context_menu_anchor, options);
+        }
+    }
+
+    //Select and context menus answer a single question, they replace whatever was shown
+    fn replace_menus(&mut self, anchor: (f32, f32), options: Vec<String>) {
+        self.menu_to_show = vec![(anchor, options)];
+        self.selected_menu_option = None;
+    }
+
+    //Positioned menus stack, the newest one becomes the active one
+    fn push_menu(&mut self, anchor: (f32, f32), options: Vec<String>) {
+        self.menu_to_show.push((anchor, options));
+        self.selected_menu_option = None;
+    }
+
+    fn active_menu(&self) -> Option<&Vec<String>> {
+        self.menu_to_show.last().map(|(_, options)| options)
+    }
+
+    fn handle_menu(&mut self, message: MessageContent) {
+        self.idle.mark_active();
+        if let Some((anchor, options)) = self.router.decode::<((f32, f32), Vec<String>)>(Topic::MENU, message.content.as_slice()) {
+            self.push_menu(anchor, options);
+        }
+    }
+
+    //The answered menu goes away, the one below becomes active again
+    fn send_menu_selection(&mut self, menu_option: usize) {
+        self.router.send(Topic::SELECT_RESPONSE, &menu_option);
+        self.menu_to_show.pop();
+        self.selected_menu_option = None;
+    }
+
+    //Wraps around at both ends of the menu
+    fn move_menu_selection(&mut self, offset: i32) {
+        let len = self.active_menu().map_or(0, |options| options.len()) as i32;
+        if len == 0 {
+            return;
+        }
+
+        let selected = match self.selected_menu_option {
+            Some(selected) => (selected as i32 + offset).rem_euclid(len),
+            None if offset < 0 => len - 1,
+            None => 0
+        };
+        self.selected_menu_option = Some(selected as usize);
+    }
+
+    fn confirm_menu_selection(&mut self) {
+        if self.active_menu().map_or(true, |options| options.is_empty()) {
+            return;
+        }
+
+        if let Some(menu_option) = self.selected_menu_option {
+            self.send_menu_selection(menu_option);
+        }
+    }
+
+    fn handle_key_down(&mut self, input: KeyInput) {
+        if self.handle_name_input_key(input) {
+            return;
+        }
+
+        //Like clicks, keys answering the game wait for the pause to end
+        match input.keycode {
+            Some(KeyCode::Up) => self.move_menu_selection(-1),
+            Some(KeyCode::Down) => self.move_menu_selection(1),
+            Some(KeyCode::Return) | Some(KeyCode::NumpadEnter) if !self.paused => self.confirm_menu_selection(),
+            Some(KeyCode::PageUp) => self.scroll_log_up(LOG_VISIBLE_LINES),
+            Some(KeyCode::PageDown) => self.scroll_log_down(LOG_VISIBLE_LINES),
+            Some(KeyCode::Home) => self.scroll_log_up(self.max_log_scroll()),
+            Some(KeyCode::End) => self.scroll_log_down(self.log_scroll),
+            Some(KeyCode::Escape) if !self.paused && !self.modals.is_empty() => self.close_modal(),
+            Some(KeyCode::Escape) if !self.paused => self.set_selection(None),
+            Some(KeyCode::Tab) if !self.paused => self.select_next_creature(),
+            Some(KeyCode::F1) => self.show_help = !self.show_help,
+            Some(KeyCode::F3) => self.toggle_fps_overlay(),
+            Some(KeyCode::G) => self.toggle_grid(),
+            Some(KeyCode::F5) => self.reload_theme(),
+            Some(KeyCode::P) => self.set_paused(!self.paused),
+            Some(KeyCode::F8) => self.set_frozen(!self.frozen),
+            Some(KeyCode::F9) => self.step_requested = self.frozen,
+            Some(KeyCode::Left) => self.scroll_log_horizontally(-LOG_HORIZONTAL_SCROLL_STEP),
+            Some(KeyCode::Right) => self.scroll_log_horizontally(LOG_HORIZONTAL_SCROLL_STEP),
+            _ => ()
+        }
+    }
+
+    fn handle_mouse_button_down(&mut self, button: MouseButton, x: f32, y: f32, space_held: bool) {
+        if button == MouseButton::Left && self.minimap.contains(self.viewport, x, y) {
+            if self.minimap.recenter_on_click {
+                self.center_camera_on_minimap(x, y);
+            }
+            self.minimap_drag = Some(Vec2::new(x, y));
+            self.camera_velocity = Vec2::ZERO;
+            return;
+        }
+
+        if button == MouseButton::Middle || (button == MouseButton::Left && space_held) {
+            self.start_drag(button, x, y);
+        }
+    }
+
+    fn handle_mouse_motion(&mut self, x: f32, y: f32, time: Instant) {
+        self.drag_to(x, y, time);
+        self.drag_minimap_to(x, y);
+    }
+
+    fn handle_mouse_button_up(&mut self, button: MouseButton, x: f32, y: f32) {
+        if self.minimap_drag.is_some() && button == MouseButton::Left {
+            self.minimap_drag = None;
+            return;
+        }
+
+        //Other buttons released during a drag are ignored, the drag goes on
+        if let Some((_, _, drag_button)) = self.drag {
+            if button == drag_button {
+                self.end_drag();
+            }
+            return;
+        }
+
+        if button == MouseButton::Right {
+            self.request_context_menu(x, y);
+            return;
+        }
+
+        if button != MouseButton::Left {
+            return;
+        }
+
+        //Any click stops the camera
+        self.camera_velocity = Vec2::ZERO;
+
+        //If some modal exist, we close it on a click outside of it, clicks inside are swallowed
+        if let Some((modal_x, modal_y, content)) = self.modals.last().cloned() {
+            if !self.modal_rect(modal_x, modal_y, content.as_str()).contains(Vec2::new(x, y)) {
+                self.close_modal();
+            }
+            return;
+        }
+
+        let action_clicked = self.action_bar_buttons.iter()
+            .position(|b| b.x < x && b.x + b.w > x &&
+                b.y < y && b.y + b.h > y);
+
+        if let Some(action_index) = action_clicked {
+            if let Some(action) = self.available_actions.get(action_index) {
+                self.send_action(action);
+            }
+            return;
+        }
+
+        let button_clicked = self.menu_buttons.iter()
+            .position(|b| b.x < x && b.x + b.w > x &&
+                b.y < y && b.y + b.h > y);
+
+        if let Some(menu_option) = button_clicked {
+            //A quick second release would send the same selection twice
+            if self.last_menu_click.map_or(false, |last| last.elapsed() < MENU_CLICK_DEBOUNCE) {
+                return;
+            }
+            self.last_menu_click = Some(Instant::now());
+
+            self.send_menu_selection(menu_option);
+            return;
+        }
+
+        let sprites_selected = self.sprites_at_pixel(x, y);
+
+        //Only creatures can be selected, a click on the floor or outside the board clears the selection
+        let selected = sprites_selected.iter()
+            .find(|s| s.layer == Layer::MOVABLES)
+            .and_then(|s| s.tile());
+        self.set_selection(selected);
+
+        //We check if user has clicked on something interactable and if interactions are availables
+        if !sprites_selected.is_empty() {
+            self.mouse_hovering_characterisation(x, y, sprites_selected);
+        }
+    }
+
+    pub fn apply_config(&mut self, config: &WindowConfig) {
+        self.window_title = config.title.clone();
+        self.set_target_fps(config.target_fps);
+        self.set_idle_fps(config.idle_fps);
+        self.set_ui_scale(config.ui_scale);
+        self.set_grid_kind(config.grid_kind);
+        self.set_rng_seed(config.rng_seed);
+        self.info_timeout = config.info_timeout;
+        self.set_camera_follow(config.camera_follow, config.camera_smoothing, config.camera_dead_zone);
+        self.set_max_effects(config.max_effects);
+        self.set_dim_behind_modals(config.dim_behind_modals);
+        self.set_emit_frame_ticks(config.emit_frame_ticks);
+        for topic in &config.ack_topics {
+            self.enable_ack(*topic);
+        }
+        self.set_flash(config.flash_duration, config.shake_on_flash);
+        self.set_blink_low_life_player(config.blink_low_life_player);
+        self.set_show_turn_timer(config.show_turn_timer);
+        self.set_minimap_recenter_on_click(config.minimap_recenter_on_click);
+        self.set_layer_alpha(Layer::BACKGROUND, config.background_alpha);
+        self.set_layer_alpha(Layer::MOVABLES, config.movables_alpha);
+        self.set_layer_alpha(Layer::UI, config.ui_alpha);
+        self.set_max_log_lines(config.max_log_lines);
+        self.set_log_fade(config.log_fade_delay, config.log_min_alpha);
+        self.set_log_overflow(config.max_log_width, config.log_overflow);
+        if let Some(prefix) = &config.topic_prefix {
+            self.set_topic_prefix(prefix);
+        }
+        if config.ask_player_name {
+            self.start_name_input();
+        }
+    }
+
+    pub fn set_idle_fps(&mut self, idle_fps: u32) {
+        self.idle_fps = idle_fps.max(1);
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: u32) {
+        self.target_fps = target_fps.max(1);
+    }
+
+    //Fewer updates per second when idle, events keep being queued in between
+    fn update_fps(&self) -> u32 {
+        if self.idle.is_idle() { self.idle_fps.min(self.target_fps) } else { self.target_fps }
+    }
+
+    //Time left before the next frame, the whole loop waits so frames are not drawn faster than they are updated
+    fn frame_wait(&self, since_last_update: Duration) -> Duration {
+        (Duration::from_secs(1) / self.update_fps()).saturating_sub(since_last_update)
+    }
+
+    //Anything still animating keeps the window at full speed
+    fn track_idle_frame(&mut self) {
+        if !self.particles.is_empty() ||
+            !self.floating_texts.is_empty() ||
+            !self.pings.is_empty() ||
+            !self.projectiles.is_empty() ||
+            !self.weather.particles.is_empty() ||
+            !self.flashes.is_empty() ||
+            self.turn_timer.is_some() ||
+            self.damage_source.is_some() ||
+            (self.log_alpha < 1. && self.log_alpha > self.log_min_alpha) ||
+            self.sprites.iter().any(|s| s.layer == Layer::MOVABLES && self.is_low_life(s)) ||
+            self.camera_velocity != Vec2::ZERO ||
+            self.drag.is_some() {
+            self.idle.mark_active();
+        }
+
+        self.idle.end_frame();
+    }
+
+    pub fn start_name_input(&mut self) {
+        self.name_input = Some(NameInput::new(MAX_PLAYER_NAME_LENGTH));
+    }
+
+    pub fn is_naming_player(&self) -> bool {
+        self.name_input.is_some()
+    }
+
+    //The name is only sent once, the start screen goes away with it
+    fn submit_player_name(&mut self) {
+        let name = self.name_input.as_ref().and_then(|input| input.submit());
+        if let Some(name) = name {
+            self.router.send(Topic::PLAYER_NAME, &name);
+            self.name_input = None;
+        }
+    }
+
+    //Returns true when the key was used by the start screen
+    fn handle_name_input_key(&mut self, input: KeyInput) -> bool {
+        let name_input = match self.name_input.as_mut() {
+            Some(name_input) => name_input,
+            None => return false
+        };
+
+        match input.keycode {
+            Some(KeyCode::Back) => name_input.backspace(),
+            Some(KeyCode::Return) | Some(KeyCode::NumpadEnter) => self.submit_player_name(),
+            _ => ()
+        }
+        true
+    }
+
+    fn handle_text_input(&mut self, character: char) {
+        if let Some(name_input) = self.name_input.as_mut() {
+            name_input.push(character);
+        }
+    }
+
+    fn draw_name_input(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let name_input = match &self.name_input {
+            Some(name_input) => name_input,
+            None => return Ok(())
+        };
+
+        let backdrop = Mesh::new_rectangle(ctx,
+                                           DrawMode::fill(),
+                                           Rect::new(0., 0., self.viewport.0, self.viewport.1),
+                                           Theme::color(self.theme.modal_backdrop_color))?;
+        canvas.draw(&backdrop, Vec2::new(0., 0.));
+
+        let padding = self.theme.panel_padding;
+        let line_height = self.theme.menu_line_height * self.text_scale();
+        let panel = Rect::new(self.viewport.0 / 4.,
+                              self.viewport.1 / 2. - line_height - padding,
+                              self.viewport.0 / 2.,
+                              2. * line_height + 2. * padding);
+        self.draw_panel(canvas, 0, panel);
+
+        let lines = ["Name your character".to_string(), format!("{}_", name_input.text())];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(canvas,
+                      &self.ui_text(line.as_str()),
+                      Vec2::new(panel.x + padding, panel.y + padding + i as f32 * line_height),
+                      graphics::DrawParam::new()
+                          .color(Theme::color(self.theme.text_color))
+                          .scale(Vec2::splat(self.text_scale())),
+                      self.theme.text_shadow());
+        }
+        Ok(())
+    }
+
+    //Sent once on the quit topic, the gameplay stops after its current turn
+    //A gameplay already gone is only reported, the window closes anyway
+    fn notify_quit(&self) {
+        if !self.router.signal(Topic::QUIT) {
+            log::warn!("The gameplay was not told that the window closed");
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn draw_pause_banner(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let backdrop = Mesh::new_rectangle(ctx,
+                                           DrawMode::fill(),
+                                           Rect::new(0., 0., self.viewport.0, self.viewport.1),
+                                           Theme::color(self.theme.modal_backdrop_color))?;
+        canvas.draw(&backdrop, Vec2::new(0., 0.));
+
+        let scale = self.text_scale() * 3.;
+        let text = self.ui_text("PAUSED");
+        let size = text.dimensions(ctx).map(|r| Vec2::new(r.w, r.h)).unwrap_or_default() * scale;
+        draw_text(canvas,
+                  &text,
+                  (Vec2::new(self.viewport.0, self.viewport.1) - size) / 2.,
+                  graphics::DrawParam::new()
+                      .color(Theme::color(self.theme.text_color))
+                      .scale(Vec2::new(scale, scale)),
+                  self.theme.text_shadow());
+        Ok(())
+    }
+
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+        self.step_requested = false;
+    }
+
+    //Channels are not read while paused or frozen, messages wait in them until the next step
+    fn process_channels_if_running(&mut self) {
+        if !self.paused && (!self.frozen || self.step_requested) {
+            self.step_requested = false;
+            self.process_channels();
+        }
+    }
+
+    fn handle_clear(&mut self, _message: MessageContent) {
+        self.idle.mark_active();
+        self.stdout.clear();
+    }
+
+    fn handle_stdout(&mut self, message: MessageContent) {
+        self.idle.mark_active();
+        self.last_log_message = Instant::now();
+        self.log_alpha = 1.;
+        if let Some(text) = self.router.decode_text(Topic::STDOUT, message.content.as_slice()) {
+            let displayed_lines = self.display_log_lines().len();
+            let out = format!("{}\n{}", self.stdout, text);
+            self.stdout = out;
+
+            //Keep the lines the user is reading in place
+            if !self.log_auto_follow {
+                let added_lines = self.display_log_lines().len() - displayed_lines;
+                self.scroll_log_up(added_lines);
+            }
+            self.trim_log();
+        }
+    }
+
+    fn handle_select(&mut self, message: MessageContent) {
+        self.idle.mark_active();
+        if let Some(options) = self.router.decode::<Vec<String>>(Topic::SELECT, message.content.as_slice()) {
+            self.replace_menus(DEFAULT_MENU_POSITION, options);
+        }
+    }
+
+    //Moves and removals are applied on the current sprites, the draw lists are then rebuilt from them
+    fn handle_sprite_delta(&mut self, message: MessageContent) {
+        self.idle.mark_active();
+        if let Some(delta) = self.router.decode::<SpriteDelta>(Topic::SPRITE_DELTA, message.content.as_slice()) {
+            //Applied on a copy, set_sprites compares with the current sprites to start the slides
+            let mut sprites = self.sprites.clone();
+            delta.apply(&mut sprites);
+            self.set_sprites(sprites);
+            self.acknowledge(Topic::SPRITE_DELTA, message.request_id);
+        }
+    }
+
+    //An empty name means nobody is playing, the banner is hidden
+    fn handle_turn(&mut self, message: MessageContent) {
+        self.idle.mark_active();
+        if let Some((name, playable)) = self.router.decode::<(String, bool)>(Topic::TURN, message.content.as_slice()) {
+            self.current_turn = if name.is_empty() { None } else { Some((name, playable)) };
+        }
+    }
+
+    fn turn_banner_text(&self) -> Option<String> {
+        match &self.current_turn {
+            Some((_, true)) => Some("Your turn".to_string()),
+            Some((name, false)) => Some(format!("{}'s turn", name)),
+            None => None
+        }
+    }
+
+    fn draw_turn_banner(&self, ctx: &Context, canvas: &mut Canvas) {
+        if let Some(banner) = self.turn_banner_text() {
+            let text = self.ui_text(banner);
+            let width = text.dimensions(ctx).map_or(0., |r| r.w) * self.text_scale();
+            draw_text(canvas,
+                      &text,
+                      Vec2::new((self.viewport.0 - width) / 2., self.theme.panel_padding * 2. + TURN_TIMER_SIZE.y),
+                      graphics::DrawParam::new()
+                          .color(Theme::color(self.theme.text_color))
+                          .scale(Vec2::splat(self.text_scale())),
+                      self.theme.text_shadow());
+        }
+    }
+
+    fn handle_sprite(&mut self, message: MessageContent) {
+        self.idle.mark_active();
+        if let Some(sprites) = self.router.decode_with(Topic::SPRITE, message.content.as_slice(), decode_sprites) {
+            self.set_sprites(sprites);
+            self.acknowledge(Topic::SPRITE, message.request_id);
+        }
+    }
+
+    fn process_channels(&mut self) {
+        self.set_available_actions();
+        self.set_weather();
+        self.set_combat_events();
+        self.set_pings();
+        self.set_projectiles();
+        self.set_aoe_preview();
+        self.set_turn_timer();
+        self.set_damage_source();
+        self.set_context_menu();
+        self.set_gameplay_state();
+
+        if let Some(message) = self.router.try_recv_raw(Topic::CLEAR) {
+            self.handle_clear(message);
+        }
+        if let Some(message) = self.router.try_recv_raw(Topic::STDOUT) {
+            self.handle_stdout(message);
+        }
+        if let Some(message) = self.router.try_recv_raw(Topic::SELECT) {
+            self.handle_select(message);
+        }
+        if let Some(message) = self.router.try_recv_raw(Topic::MENU) {
+            self.handle_menu(message);
+        }
+        if let Some(message) = self.router.try_recv_raw(Topic::SPRITE) {
+            self.handle_sprite(message);
+        }
+        if let Some(message) = self.router.try_recv_raw(Topic::SPRITE_DELTA) {
+            self.handle_sprite_delta(message);
+        }
+        if let Some(message) = self.router.try_recv_raw(Topic::TURN) {
+            self.handle_turn(message);
+        }
+
+        if let Some(state) = self.gameplay_state.clone() {
+            self.idle.mark_active();
+            match state {
+                Actions::OPEN => {}
+                Actions::ATTACK => self.wait_for_attack(),
+                Actions::WALK_TO => {}
+                Actions::WATCH => self.wait_for_watch(),
+                Actions::USE => {}
+                Actions::EQUIP => {}
+                Actions::MOVE => {}
+            }
+        }
+    }
+
+    pub fn queue_input(&mut self, event: InputEvent) {
+        self.input_queue.push_back(event);
+    }
+
+    fn process_input_queue(&mut self) {
+        if !self.has_valid_viewport() {
+            self.input_queue.clear();
+            return;
+        }
+
+        if !self.input_queue.is_empty() {
+            self.idle.mark_active();
+        }
+        while let Some(event) = self.input_queue.pop_front() {
+            self.dispatch_input(event);
+        }
+    }
+
+    pub fn start_macro_recording(&mut self, name: &str) {
+        self.input_macros.start_recording(name);
+    }
+
+    pub fn stop_macro_recording(&mut self) {
+        self.input_macros.stop_recording();
+    }
+
+    pub fn bind_macro(&mut self, key: KeyCode, name: &str) {
+        self.input_macros.bind(key, name);
+    }
+
+    fn replay_macro(&mut self, name: &str) {
+        if !self.input_macros.begin_replay(name) {
+            log::debug!("Macro {} is already running", name);
+            return;
+        }
+
+        for event in self.input_macros.events(name) {
+            self.dispatch_input(event);
+        }
+        self.input_macros.end_replay(name);
+    }
+
+    fn dispatch_input(&mut self, event: InputEvent) {
+        if let InputEvent::KeyDown(input) = event {
+            match input.keycode {
+                //F6 toggles the recording of the quick macro, which is replayed with F7
+                Some(KeyCode::F6) => {
+                    if self.input_macros.is_recording() {
+                        self.stop_macro_recording();
+                    } else {
+                        self.start_macro_recording(QUICK_MACRO);
+                        self.bind_macro(KeyCode::F7, QUICK_MACRO);
+                    }
+                    return;
+                }
+                Some(key) => {
+                    if let Some(name) = self.input_macros.bound_macro(key) {
+                        self.replay_macro(name.as_str());
+                        return;
+                    }
+                }
+                None => ()
+            }
+        }
+
+        self.input_macros.record(event);
+        match event {
+            InputEvent::KeyDown(input) => self.handle_key_down(input),
+            InputEvent::TextInput(character) => self.handle_text_input(character),
+            //Clicks would act on the game, only the keyboard can resume it or name the player
+            InputEvent::MouseDown { .. } | InputEvent::MouseUp { .. } if self.paused || self.is_naming_player() => (),
+            InputEvent::MouseDown { button, x, y, space_held } => self.handle_mouse_button_down(button, x, y, space_held),
+            InputEvent::MouseMotion { x, y, time } => self.handle_mouse_motion(x, y, time),
+            InputEvent::MouseUp { button, x, y } => self.handle_mouse_button_up(button, x, y),
+            InputEvent::MouseWheel { x, y, notches } => self.handle_mouse_wheel(x, y, notches),
+        }
+    }
+}
+
+impl event::EventHandler<ggez::GameError> for MainState {
+    fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, _repeated: bool) -> Result<(), GameError> {
+        self.idle.mark_active();
+        self.queue_input(InputEvent::KeyDown(input));
+        Ok(())
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> Result<(), GameError> {
+        self.idle.mark_active();
+        self.queue_input(InputEvent::TextInput(character));
+        Ok(())
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> Result<(), GameError> {
+        self.idle.mark_active();
+        let space_held = ctx.keyboard.is_key_pressed(KeyCode::Space);
+        self.queue_input(InputEvent::MouseDown { button, x, y, space_held });
+        Ok(())
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) -> Result<(), GameError> {
+        self.idle.mark_active();
+        self.queue_input(InputEvent::MouseMotion { x, y, time: Instant::now() });
+        Ok(())
+    }
+
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> Result<(), GameError> {
+        self.idle.mark_active();
+        self.queue_input(InputEvent::MouseUp { button, x, y });
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) -> Result<(), GameError> {
+        self.idle.mark_active();
+        let position = ctx.mouse.position();
+        self.queue_input(InputEvent::MouseWheel { x: position.x, y: position.y, notches: y });
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let wait = self.frame_wait(self.last_update.elapsed());
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+        let delta = self.last_update.elapsed();
+        self.last_update = Instant::now();
+
+        let point2 = ctx.mouse.position();
+        self.viewport = ctx.gfx.drawable_size();
+        self.tick_frame(ctx.time.time_since_start().as_secs_f64());
+
+        self.process_channels_if_running();
+
+        //Input is handled once every channel has been drained so it acts on the freshest state
+        self.process_input_queue();
+
+        self.surface_router_errors();
+        self.prune_toasts(Instant::now());
+
+        if self.has_valid_viewport() {
+            self.follow_camera(delta.as_secs_f32(), self.viewport);
+            self.apply_camera_momentum(delta.as_secs_f32(), self.viewport);
+            self.weather.update(delta.as_secs_f32(), self.viewport, &mut self.rng);
+        }
+        self.mouse.set_pointer_position(point2.x, point2.y);
+        self.update_aoe_preview();
+        if !self.paused {
+            self.update_hover();
+        }
+        self.update_log_alpha(delta.as_secs_f32());
+        self.animator.advance(1., delta.as_secs_f64());
+
+        self.particles.retain(|p: &(u16, DrawParam, Instant, u8)|  p.2.elapsed() < Duration::new(self.animation_duration as u64,0));
+
+        self.floating_texts.retain(|t| !t.is_expired());
+        self.pings.retain(|p| !p.is_expired());
+        self.projectiles.retain(|p| !p.is_expired());
+        self.update_shake_jitter();
+        if self.damage_source_strength() <= 0. {
+            self.damage_source = None;
+        }
+        if !self.frozen && !self.paused {
+            self.update_turn_timer(delta.as_secs_f32());
+        }
+
+        self.movements.retain(|_, (_, start)| start.elapsed() < MOVE_DURATION);
+
+        self.expire_flashes();
+
+        self.track_idle_frame();
+
+        Ok(())
+    }
+
+    fn quit_event(&mut self, ctx: &mut Context) -> Result<bool, GameError> {
+        //Saved in logical pixels, like the size the window is created with
+        let window = ctx.gfx.window();
+        let size = window.inner_size().to_logical::<f32>(window.scale_factor());
+        WindowState { width: size.width, height: size.height }.save(WINDOW_STATE_PATH);
+        self.notify_quit();
+        Ok(false)
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> Result<(), GameError> {
+        self.idle.mark_active();
+        self.viewport = (width, height);
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        if self.headless || !self.has_valid_viewport() {
+            return Ok(());
+        }
+
+        if self.background_dirty {
+            self.rebuild_background_batches(ctx);
+        }
+
+        let frame_start = Instant::now();
+        let mut stats = RenderStats::default();
+        let fps = self.current_fps(ctx);
+        ctx.gfx.set_window_title(format!("{0} fps: {1:.0}", self.window_title, fps).as_str());
+        let mut canvas = Canvas::from_frame(
+            ctx,
+            self.void_color(),
+        );
+        let (width, height) = ctx.gfx.drawable_size();
+
+        //World layers are drawn through the camera, the UI stays in screen coordinates
+        let (world_width, world_height) = self.visible_world_size((width, height));
+        let visible_world = Rect::new(self.camera.x, self.camera.y, world_width, world_height);
+        canvas.set_screen_coordinates(visible_world);
+        for batch in &self.background_batches {
+            let visible = batch.bounds.overlaps(&visible_world);
+            stats.count_batch(batch.sprite_count, visible);
+            if visible {
+                canvas.draw(&batch.instances, DrawParam::new());
+            }
+        }
+        for mesh in &self.watchable_overlay {
+            if let Some(texture) = self.texture(mesh.0) {
+                canvas.draw(texture, Self::with_layer_alpha(mesh.1, self.ui_alpha));
+            }
+        }
+        stats.count_draw_calls(self.watchable_overlay.len());
+        let time = ctx.time.time_since_start().as_secs_f32();
+        //Movables slide and blink, so their batches are rebuilt each frame from the visible ones
+        let movables = self.sprites.iter().filter(|s| s.layer == Layer::MOVABLES);
+        let mut visible_movables = vec![];
+        for (sprite, mesh) in movables.zip(&self.sprites_movables) {
+            let visible = self.is_on_screen(sprite);
+            stats.count_sprite(visible);
+            if !visible {
+                continue;
+            }
+            let param = mesh.1
+                .dest(self.movable_position(sprite))
+                .src(sprite.frame_rect(time));
+            visible_movables.push((mesh.0, Self::with_layer_alpha(param, self.movables_alpha * self.low_life_alpha(sprite, time))));
+        }
+        for (texture_id, params) in texture_runs(visible_movables) {
+            if let Some(texture) = self.texture(texture_id) {
+                let mut instances = InstanceArray::new(ctx, texture.clone());
+                params.into_iter().for_each(|param| instances.push(param));
+                canvas.draw(&instances, DrawParam::new());
+                stats.count_draw_calls(1);
+            }
+        }
+        if self.draw_grid(ctx, &mut canvas)? {
+            stats.count_meshes(1);
+        }
+        if self.draw_health_bars(ctx, &mut canvas)? {
+            stats.count_meshes(1);
+        }
+        if self.draw_hover_highlight(ctx, &mut canvas)? {
+            stats.count_meshes(1);
+        }
+        if self.draw_selection_outline(ctx, &mut canvas)? {
+            stats.count_meshes(1);
+        }
+        self.draw_aoe_preview(ctx, &mut canvas)?;
+        if let Some(preview) = &self.aoe_preview {
+            stats.count_meshes(preview.tiles(self.board_size()).len());
+        }
+        self.draw_flashes(ctx, &mut canvas)?;
+        stats.count_meshes(self.flashes.len());
+        self.draw_damage_source(ctx, &mut canvas)?;
+        if self.damage_source.is_some() {
+            stats.count_meshes(1);
+        }
+        for particle in &self.particles {
+            if let Some(texture) = self.texture(particle.0) {
+                canvas.draw(texture, particle.1
+                    .src(self.animator.get_currenct_rect(particle.3 as usize)));
+            }
+        }
+        stats.count_draw_calls(self.particles.len());
+        //UI sprites (selectors, targetable cells) are anchored to tiles, so they go through the camera
+        //and are drawn after the movables to stay on top of them
+        for mesh in &self.sprites_ui {
+            if let Some(texture) = self.texture(mesh.0) {
+                canvas.draw(texture, Self::with_layer_alpha(mesh.1, self.ui_alpha));
+            }
+        }
+        stats.count_draw_calls(self.sprites_ui.len());
+        for floating_text in &self.floating_texts {
+            floating_text.draw(&mut canvas, self.theme.text_shadow());
+        }
+        stats.count_draw_calls(self.floating_texts.len());
+        for ping in &self.pings {
+            ping.draw(ctx, &mut canvas, Theme::color(self.theme.ping_color))?;
+        }
+        stats.count_meshes(self.pings.len());
+        for projectile in &self.projectiles {
+            projectile.draw(ctx, &mut canvas, Theme::color(self.theme.projectile_color))?;
+        }
+        stats.count_meshes(self.projectiles.len());
+        canvas.set_screen_coordinates(Rect::new(0., 0., width, height));
+        let weather_color = match self.weather.kind {
+            WeatherKind::RAIN => self.theme.rain_color,
+            _ => self.theme.snow_color
+        };
+        self.weather.draw(ctx, &mut canvas, self.camera, (width, height), Theme::color(weather_color))?;
+        if !self.weather.particles.is_empty() {
+            stats.count_meshes(1);
+        }
+
+        self.layout_menu_buttons();
+        let menus = self.menu_to_show.clone();
+        for (i, (anchor, options)) in menus.into_iter().enumerate() {
+            self.draw_menu(ctx, &mut canvas, anchor, options, i + 1 == self.menu_to_show.len())?;
+        }
+
+        self.draw_log(&mut canvas);
+
+        self.draw_action_bar(ctx, &mut canvas);
+        self.draw_turn_timer(ctx, &mut canvas)?;
+        self.draw_turn_banner(ctx, &mut canvas);
+        self.draw_fps_overlay(ctx, &mut canvas);
+        if self.draw_toasts(ctx, &mut canvas)? {
+            stats.count_meshes(1);
+        }
+        let minimap_dots = self.minimap_dots();
+        self.minimap.draw(ctx, &mut canvas, (width, height), self.camera, self.world_size(), self.zoom, &minimap_dots, self.tile_size as f32, Theme::color(self.theme.minimap_background_color))?;
+        stats.count_meshes(if minimap_dots.is_empty() { 2 } else { 3 });
+        let markers = self.edge_markers();
+        for marker in &markers {
+            marker.draw(ctx, &mut canvas, self.tile_size as f32, Theme::color(self.theme.edge_marker_color))?;
+        }
+        stats.count_meshes(markers.len());
+        self.draw_damage_source_marker(ctx, &mut canvas)?;
+
+        if self.is_dimming_behind_modals() {
+            self.draw_modal_backdrop(ctx, &mut canvas)?;
+        }
+        for (x, y, content) in self.modals.clone() {
+            self.draw_modal(&mut canvas, x, y, content.as_str())?;
+        }
+
+        if self.paused {
+            self.draw_pause_banner(ctx, &mut canvas)?;
+            stats.count_meshes(1);
+        }
+
+        if self.is_naming_player() {
+            self.draw_name_input(ctx, &mut canvas)?;
+            stats.count_meshes(1);
+        }
+
+        if self.show_help {
+            self.draw_help(ctx, &mut canvas)?;
+        }
+
+        canvas.draw(&self.mouse.get_mesh(&ctx, self.theme.cursor_size, &self.gameplay_state, Theme::color(self.theme.cursor_color)), Vec2::new(0.0, 0.0));
+        stats.count_meshes(1);
+
+
+        canvas.finish(ctx)?;
+        stats.frame_time = frame_start.elapsed();
+        self.render_stats = stats;
+        Ok(())
+    }
+}
+
+//Settings for one try at creating the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ContextAttempt {
+    samples: NumSamples,
+    backend: Backend,
+}
+
+//Each attempt lowers the requirements of the previous one, so low end machines still get a window
+fn context_attempts(config: &WindowConfig) -> Vec<ContextAttempt> {
+    let mut attempts = vec![ContextAttempt { samples: config.samples, backend: Backend::All }];
+    if config.samples != NumSamples::One {
+        attempts.push(ContextAttempt { samples: NumSamples::One, backend: Backend::All });
+    }
+    attempts.push(ContextAttempt { samples: NumSamples::One, backend: Backend::Gl });
+    attempts
+}
+
+fn window_conf(config: &WindowConfig, attempt: ContextAttempt) -> (WindowMode, WindowSetup) {
+    (WindowMode::default().dimensions(config.width, config.height),
+     WindowSetup::default().title(config.title.as_str()).samples(attempt.samples))
+}
+
+fn context_builder(config: &WindowConfig, attempt: ContextAttempt) -> ContextBuilder {
+    let (window_mode, window_setup) = window_conf(config, attempt);
+    let builder = ContextBuilder::new("super simple", "ggez")
+        .window_mode(window_mode)
+        .window_setup(window_setup)
+        .backend(attempt.backend);
+
+    match &config.resource_path {
+        Some(path) => builder.add_resource_path(path.clone()),
+        None => builder
+    }
+}
+
+fn build_context(config: &WindowConfig) -> GameResult<(Context, event::EventLoop<()>)> {
+    let attempts = context_attempts(config);
+    let mut last_error = None;
+    for (i, attempt) in attempts.iter().enumerate() {
+        match context_builder(config, *attempt).build() {
+            Ok(context) => return Ok(context),
+            Err(e) => {
+                if let Some(next) = attempts.get(i + 1) {
+                    log::warn!("Cannot create the window with {:?} : {}, retrying with {:?}", attempt, e, next);
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+
+    let error = last_error.map_or("no settings to try".to_string(), |e| e.to_string());
+    log::error!("Cannot create the window after {} attempts : {}, check that a display is available", attempts.len(), error);
+    Err(GameError::CustomError(format!("Cannot create the window : {}", error)))
+}
+
+//The size of the last session wins over the default one
+fn startup_config(resource_path: Option<&str>, saved: Option<WindowState>) -> WindowConfig {
+    let mut config = WindowConfig {
+        resource_path: resource_path.map(PathBuf::from),
+        ..Default::default()
+    };
+    if let Some(saved) = saved {
+        config.width = saved.width;
+        config.height = saved.height;
+    }
+    config
+}
+
+pub fn init(receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>, load_path: Option<&str>, tile_size: i32, resource_path: Option<&str>) -> GameResult {
+    let config = startup_config(resource_path, WindowState::load(WINDOW_STATE_PATH));
+    init_with_config(receivers, senders, load_path, tile_size, config)
+}
+
+pub fn init_with_config(receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>, load_path: Option<&str>, tile_size: i32, config: WindowConfig) -> GameResult {
+    let (mut ctx, event_loop) = build_context(&config)?;
+
+
+    let mut state = MainState::new(&ctx, receivers, senders, tile_size, config.texture_manifest.as_deref())?;
+    state.apply_config(&config);
+    if let Some(path) = &config.font_path {
+        match FontData::from_path(&ctx, path) {
+            Ok(font) => {
+                ctx.gfx.add_font(UI_FONT, font);
+                state.set_font(Some(UI_FONT));
+            }
+            Err(e) => log::warn!("Cannot load font {} : {}, using the default one", path, e)
+        }
+    }
+    if let Some(path) = load_path {
+        state.load_board(path);
+    }
+    event::run(ctx, event_loop, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Mutex, Once};
+    use std::sync::mpsc::channel;
+    use std::time::{Duration, Instant};
+    use ggez::conf::{Backend, NumSamples};
+    use ggez::event::MouseButton;
+    use ggez::glam::Vec2;
+    use ggez::graphics::{Color, DrawParam, Rect, Transform};
+    use ggez::input::keyboard::{KeyCode, KeyInput, KeyMods};
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use crate::gui::graphical::combat_event::{CombatEvent, CombatEventKind};
+    use crate::gui::graphical::floating_text::FloatingText;
+    use crate::gui::graphical::grid::GridKind;
+    use crate::gui::graphical::router::Topic;
+    use crate::gui::graphical::sprite::{encode_sprites, Layer, Sprite};
+    use crate::interact::actions::Actions;
+    use crate::services::messaging::MessageContent;
+    use crate::gui::graphical::log::{LOG_CHAR_WIDTH, LogOverflow};
+    use crate::gui::graphical::input::InputEvent;
+    use crate::gui::graphical::window_state::WindowState;
+    use crate::gui::graphical::effects::{Ping, Projectile};
+    use super::{context_attempts, ContextAttempt, DAMAGE_SOURCE_DURATION, IDLE_FRAMES_THRESHOLD, LOG_HORIZONTAL_SCROLL_STEP, MainState, MAX_TOASTS, MAX_ZOOM, MIN_ZOOM, Mouse, startup_config, TOAST_DURATION, TOOLTIP_DELAY, WATCHABLE_OVERLAY_ALPHA, window_conf, WindowConfig};
+
+    struct CapturingLogger {
+        records: Mutex<Vec<(Level, String)>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.records.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger { records: Mutex::new(vec![]) };
+    static INIT_LOGGER: Once = Once::new();
+
+    fn key(keycode: KeyCode) -> KeyInput {
+        KeyInput { scancode: 0, keycode: Some(keycode), mods: KeyMods::empty() }
+    }
+
+    fn dest(param: &DrawParam) -> Vec2 {
+        match param.transform {
+            Transform::Values { dest, .. } => Vec2::new(dest.x, dest.y),
+            Transform::Matrix(_) => panic!("sprites are placed by values"),
+        }
+    }
+
+    //The logger is global, every test sharing it only looks for its own lines
+    fn captured_logs() -> &'static CapturingLogger {
+        INIT_LOGGER.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(LevelFilter::Trace);
+        });
+        &LOGGER
+    }
+
+    #[test]
+    fn hovering_a_sprite_logs_at_debug_level() {
+        let logger = captured_logs();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites(vec![Sprite::new(1, 2, 1, Layer::BACKGROUND)]);
+        state.gameplay_state = Some(Actions::WATCH);
+        state.mouse.set_pointer_position(70., 40.);
+        state.hover = Some(((2, 1), Instant::now() - TOOLTIP_DELAY));
+
+        state.update_hover();
+
+        let records = logger.records.lock().unwrap();
+        assert!(records.contains(&(Level::Debug, "Hovering tile Some((2, 1))".to_string())));
+    }
+
+    #[test]
+    fn movables_are_drawn_by_ascending_z() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        state.set_sprites(vec![
+            Sprite::new(10, 0, 0, Layer::MOVABLES).with_z(2),
+            Sprite::new(11, 1, 0, Layer::MOVABLES).with_z(0),
+            Sprite::new(12, 2, 0, Layer::MOVABLES).with_z(1),
+        ]);
+
+        let textures = state.sprites_movables.iter().map(|(texture_id, _)| *texture_id).collect::<Vec<_>>();
+        assert_eq!(textures, vec![11, 12, 10]);
+    }
+
+    #[test]
+    fn effects_budget_evicts_the_oldest_effect_of_both_kinds() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let now = Instant::now();
+        let text = |age: u64| FloatingText {
+            text: String::new(),
+            position: Vec2::ZERO,
+            color: Color::WHITE,
+            scale: 1.,
+            start: now - Duration::from_millis(age),
+        };
+        state.set_max_effects(3);
+        state.push_floating_text(text(300));
+        state.push_particle((1, DrawParam::new(), now - Duration::from_millis(200), 0));
+        state.push_floating_text(text(100));
+
+        state.push_particle((1, DrawParam::new(), now, 0));
+
+        assert_eq!(state.active_effects_count(), 3);
+        assert_eq!(state.floating_texts.len(), 1);
+        assert_eq!(state.floating_texts[0].start, now - Duration::from_millis(100));
+        assert_eq!(state.particles.len(), 2);
+    }
+
+    #[test]
+    fn ack_echoes_the_version_of_the_sprite_frame() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("ack".to_string(), sender)]));
+        state.enable_ack(Topic::SPRITE);
+
+        state.handle_sprite(MessageContent {
+            topic: "sprite".to_string(),
+            content: bincode::serialize(&Vec::<Sprite>::new()).unwrap(),
+            request_id: Some(42),
+        });
+
+        let ack = receiver.try_recv().unwrap();
+        assert_eq!(bincode::deserialize::<(String, u64)>(ack.content.as_slice()).unwrap(), ("sprite".to_string(), 42));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn toggling_the_grid_flips_the_overlay() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        assert!(!state.show_grid);
+
+        state.toggle_grid();
+        assert!(state.show_grid);
+
+        state.toggle_grid();
+        assert!(!state.show_grid);
+    }
+
+    #[test]
+    fn hex_screen_to_tile_picks_the_closest_side_of_a_shared_edge() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.tile_size = 32;
+        state.set_sprites((0..4).map(|i| Sprite::new(1, i % 2, i / 2, Layer::BACKGROUND)).collect());
+        state.set_grid_kind(GridKind::HEX_POINTY);
+
+        //The edge between (0, 0) and the shifted row below goes from (32, 24) to (16, 32)
+        assert_eq!(state.screen_to_tile(23., 27.), Some((0, 0)));
+        assert_eq!(state.screen_to_tile(25., 29.), Some((0, 1)));
+    }
+
+    #[test]
+    fn selection_changes_are_sent_once() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("selection_changed".to_string(), sender)]));
+
+        state.set_selection(Some((1, 2)));
+        state.set_selection(Some((1, 2)));
+
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn tab_cycles_through_the_creatures_in_reading_order() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites(vec![
+            Sprite::new(1, 3, 1, Layer::MOVABLES),
+            Sprite::new(1, 5, 0, Layer::MOVABLES),
+            Sprite::new(1, 0, 0, Layer::BACKGROUND),
+        ]);
+
+        state.select_next_creature();
+        assert_eq!(state.selection, Some((5, 0)));
+        state.select_next_creature();
+        assert_eq!(state.selection, Some((3, 1)));
+        state.select_next_creature();
+        assert_eq!(state.selection, Some((5, 0)));
+    }
+
+    #[test]
+    fn critical_hits_flash_the_target_id_until_the_duration_ends() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_flash(Duration::from_millis(300), true);
+        state.set_sprites(vec![Sprite::new(1, 2, 3, Layer::MOVABLES).with_id(7)]);
+
+        state.handle_combat_event(&CombatEvent { kind: CombatEventKind::CRITICAL, target: (2, 3), value: 12 });
+        state.expire_flashes();
+        assert!(state.flashes.contains_key(&7));
+
+        state.flashes.insert(7, (Instant::now() - Duration::from_millis(300), Color::WHITE));
+        state.expire_flashes();
+        assert!(state.flashes.is_empty());
+    }
+
+    #[test]
+    fn horizontal_log_scroll_steps_by_characters() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_log_overflow(10. * LOG_CHAR_WIDTH, LogOverflow::SCROLL);
+        state.stdout = "[dmg] 0123456789abcdefghijklmnopqrstuvwxyz".to_string();
+
+        state.scroll_log_horizontally(LOG_HORIZONTAL_SCROLL_STEP);
+        assert_eq!(state.display_log_lines()[0].1, "89abcdefgh");
+
+        for _ in 0..10 {
+            state.scroll_log_horizontally(LOG_HORIZONTAL_SCROLL_STEP);
+        }
+        assert_eq!(state.display_log_lines()[0].1, "qrstuvwxyz");
+    }
+
+    #[test]
+    fn camera_follows_the_important_creature_outside_the_dead_zone_only() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let background = (0..1600).map(|i| Sprite::new(1, i % 40, i / 40, Layer::BACKGROUND));
+        let creature = |x| Sprite::new(7, x, 10, Layer::MOVABLES).with_id(1).with_important(true);
+        state.set_sprites(background.clone().chain([creature(10), Sprite::new(8, 30, 30, Layer::MOVABLES)]).collect());
+        state.set_camera_follow(true, 5., Vec2::new(64., 64.));
+
+        state.update_camera_target((320., 320.));
+        assert_eq!(state.camera_target, Vec2::new(112., 112.));
+
+        state.set_sprites(background.chain([creature(9), Sprite::new(8, 30, 30, Layer::MOVABLES)]).collect());
+        state.update_camera_target((320., 320.));
+        assert_eq!(state.camera_target, Vec2::new(112., 112.));
+    }
+
+    #[test]
+    fn saved_boards_load_back() {
+        let path = std::env::temp_dir().join("baston_saved_board_test.bin");
+        let path = path.to_str().unwrap();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites(vec![Sprite::new(3, 1, 2, Layer::BACKGROUND)]);
+        state.stdout = "[info] Saved".to_string();
+
+        state.save_board(path).unwrap();
+        let mut loaded = MainState::new_headless(HashMap::new(), HashMap::new());
+        loaded.load_board(path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.stdout, "[info] Saved");
+        assert_eq!(loaded.sprites.iter().map(|s| (s.texture_id, s.pos_x, s.pos_y)).collect::<Vec<_>>(), vec![(3, 1, 2)]);
+    }
+
+    #[test]
+    fn drag_ends_only_on_the_release_of_its_button() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        state.handle_mouse_button_down(MouseButton::Middle, 100., 100., false);
+        state.handle_mouse_button_up(MouseButton::Left, 100., 100.);
+        assert!(state.drag.is_some());
+
+        state.handle_mouse_button_up(MouseButton::Middle, 100., 100.);
+        assert!(state.drag.is_none());
+    }
+
+    #[test]
+    fn window_creation_downgrades_msaa_then_the_backend() {
+        let config = WindowConfig { samples: NumSamples::Four, ..Default::default() };
+
+        assert_eq!(context_attempts(&config), vec![
+            ContextAttempt { samples: NumSamples::Four, backend: Backend::All },
+            ContextAttempt { samples: NumSamples::One, backend: Backend::All },
+            ContextAttempt { samples: NumSamples::One, backend: Backend::Gl },
+        ]);
+    }
+
+    #[test]
+    fn window_creation_without_msaa_only_downgrades_the_backend() {
+        let config = WindowConfig { samples: NumSamples::One, ..Default::default() };
+
+        assert_eq!(context_attempts(&config), vec![
+            ContextAttempt { samples: NumSamples::One, backend: Backend::All },
+            ContextAttempt { samples: NumSamples::One, backend: Backend::Gl },
+        ]);
+    }
+
+    #[test]
+    fn idle_rate_is_used_after_enough_quiet_frames() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.apply_config(&WindowConfig { target_fps: 60, idle_fps: 5, ..Default::default() });
+
+        for _ in 0..IDLE_FRAMES_THRESHOLD {
+            assert_eq!(state.update_fps(), 60);
+            state.track_idle_frame();
+        }
+        assert_eq!(state.update_fps(), 5);
+        assert_eq!(state.frame_wait(Duration::from_millis(50)), Duration::from_millis(150));
+
+        state.queue_input(InputEvent::TextInput('a'));
+        state.process_messages();
+        assert_eq!(state.update_fps(), 60);
+        assert_eq!(state.frame_wait(Duration::from_millis(20)), Duration::ZERO);
+    }
+
+    #[test]
+    fn configured_target_fps_paces_the_frames() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.apply_config(&WindowConfig { target_fps: 20, ..Default::default() });
+
+        assert_eq!(state.target_fps, 20);
+        assert_eq!(state.frame_wait(Duration::from_millis(10)), Duration::from_millis(40));
+        assert_eq!(state.frame_wait(Duration::from_millis(60)), Duration::ZERO);
+    }
+
+    #[test]
+    fn configured_topic_prefix_is_used_for_the_lookups() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("p1/info".to_string(), sender)]));
+        state.set_sprites(vec![Sprite::new(1, 0, 0, Layer::BACKGROUND)]);
+        state.apply_config(&WindowConfig { topic_prefix: Some("p1/".to_string()), ..Default::default() });
+
+        assert!(state.send_info_message(&10., &10.));
+
+        let message = receiver.try_recv().unwrap();
+        assert_eq!(message.topic, "p1/info");
+        assert_eq!(bincode::deserialize::<(u16, u16)>(message.content.as_slice()).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn same_configured_seed_gives_the_same_shakes() {
+        let sprite = Sprite::new(1, 0, 0, Layer::MOVABLES).with_id(7);
+        let start = Instant::now();
+        let shaking_state = || {
+            let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+            state.apply_config(&WindowConfig { rng_seed: 1234, ..Default::default() });
+            state.set_flash(Duration::from_secs(1_000_000), true);
+            state.flashes.insert(7, (start, Color::RED));
+            state
+        };
+        let mut first = shaking_state();
+        let mut second = shaking_state();
+
+        for _ in 0..5 {
+            first.update_shake_jitter();
+            second.update_shake_jitter();
+            assert_ne!(first.shake_offset(&sprite), Vec2::ZERO);
+            assert_eq!(first.shake_offset(&sprite), second.shake_offset(&sprite));
+        }
+    }
+
+    #[test]
+    fn configured_info_timeout_releases_the_watch() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("info".to_string(), sender)]));
+        state.apply_config(&WindowConfig { info_timeout: Duration::from_millis(500), ..Default::default() });
+        state.pending_info = Some(Instant::now() - Duration::from_millis(300));
+
+        state.wait_for_watch();
+        assert!(receiver.try_recv().is_err());
+
+        state.apply_config(&WindowConfig { info_timeout: Duration::from_millis(100), ..Default::default() });
+        state.wait_for_watch();
+        assert!(receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn camera_follow_is_on_with_the_default_config() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        state.apply_config(&WindowConfig::default());
+        assert!(state.camera_follow);
+
+        state.apply_config(&WindowConfig { camera_follow: false, ..Default::default() });
+        assert!(!state.camera_follow);
+    }
+
+    #[test]
+    fn effects_budget_covers_pings_and_projectiles() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let now = Instant::now();
+        state.apply_config(&WindowConfig { max_effects: 2, ..Default::default() });
+        state.push_ping(Ping { position: Vec2::ZERO, start: now - Duration::from_millis(300) });
+        state.push_projectile(Projectile { from: Vec2::ZERO, to: Vec2::ONE, start: now - Duration::from_millis(200) });
+
+        state.push_ping(Ping { position: Vec2::ZERO, start: now });
+
+        assert_eq!(state.active_effects_count(), 2);
+        assert_eq!(state.pings.len(), 1);
+        assert_eq!(state.pings[0].start, now);
+        assert_eq!(state.projectiles.len(), 1);
+        assert_eq!(state.effects_overlay_text(), "effects 2/2 : 0 particles 0 texts 1 pings 1 projectiles");
+    }
+
+    #[test]
+    fn pings_and_projectiles_are_spawned_from_their_topics() {
+        let (ping_sender, ping_receiver) = channel();
+        let (projectile_sender, projectile_receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([
+            ("ping".to_string(), ping_receiver),
+            ("projectile".to_string(), projectile_receiver),
+        ]), HashMap::new());
+        let message = |topic: &str, content| MessageContent { topic: topic.to_string(), content, request_id: None };
+
+        ping_sender.send(message("ping", bincode::serialize(&(1u16, 0u16)).unwrap())).unwrap();
+        projectile_sender.send(message("projectile", bincode::serialize(&((0u16, 0u16), (2u16, 0u16))).unwrap())).unwrap();
+        state.process_messages();
+
+        assert_eq!(state.pings[0].position, Vec2::new(48., 16.));
+        assert_eq!((state.projectiles[0].from, state.projectiles[0].to), (Vec2::new(16., 16.), Vec2::new(80., 16.)));
+    }
+
+    #[test]
+    fn move_clicks_send_empty_tiles_only() {
+        let (state_sender, state_receiver) = channel();
+        let (move_sender, move_receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("gameplay_state".to_string(), state_receiver)]),
+                                                HashMap::from([("move".to_string(), move_sender), ("selection_changed".to_string(), channel().0)]));
+        let mut sprites = (0..9).map(|i| Sprite::new(1, i % 3, i / 3, Layer::BACKGROUND)).collect::<Vec<Sprite>>();
+        sprites.push(Sprite::new(200, 1, 1, Layer::MOVABLES));
+        state.set_sprites(sprites);
+        let move_state = || MessageContent {
+            topic: "gameplay_state".to_string(),
+            content: bincode::serialize(&Actions::MOVE.to_u8()).unwrap(),
+            request_id: None,
+        };
+
+        state_sender.send(move_state()).unwrap();
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Left, x: 48., y: 48. });
+        state.process_messages();
+        assert!(move_receiver.try_recv().is_err());
+
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Left, x: 80., y: 16. });
+        state.process_messages();
+        let message = move_receiver.try_recv().unwrap();
+        assert_eq!(bincode::deserialize::<(u16, u16)>(message.content.as_slice()).unwrap(), (2, 0));
+        assert_eq!(state.gameplay_state, None);
+    }
+
+    #[test]
+    fn clicks_off_the_board_map_to_no_tile() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites((0..4).map(|i| Sprite::new(1, i % 2, i / 2, Layer::BACKGROUND)).collect());
+        state.camera = Vec2::new(-100., -100.);
+
+        assert_eq!(state.screen_to_tile(10., 10.), None);
+        assert_eq!(state.screen_to_tile(110., 110.), Some((0, 0)));
+        assert_eq!(state.screen_to_tile(200., 110.), None);
+    }
+
+    #[test]
+    fn sprite_messages_from_both_payload_versions_reach_the_draw_lists() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("sprite".to_string(), receiver)]), HashMap::new());
+        let message = |content| MessageContent { topic: "sprite".to_string(), content, request_id: None };
+
+        //A gameplay built before the texture ids were widened sends them as u8
+        sender.send(message(bincode::serialize(&vec![(201u8, 1i32, 1i32, Layer::MOVABLES)]).unwrap())).unwrap();
+        state.process_messages();
+        assert_eq!(state.sprites_movables.iter().map(|(texture_id, _)| *texture_id).collect::<Vec<_>>(), vec![201]);
+
+        sender.send(message(encode_sprites(&[Sprite::new(300, 1, 1, Layer::MOVABLES)]).unwrap())).unwrap();
+        state.process_messages();
+        assert_eq!(state.sprites_movables.iter().map(|(texture_id, _)| *texture_id).collect::<Vec<_>>(), vec![300]);
+        assert!(state.toasts.is_empty());
+    }
+
+    #[test]
+    fn released_drag_keeps_the_velocity_of_the_last_motion() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites((0..1600).map(|i| Sprite::new(1, i % 40, i / 40, Layer::BACKGROUND)).collect());
+        state.camera = Vec2::new(200., 200.);
+        let start = Instant::now();
+
+        state.queue_input(InputEvent::MouseDown { button: MouseButton::Middle, x: 400., y: 300., space_held: false });
+        for (i, x) in [390., 380., 370.].into_iter().enumerate() {
+            state.queue_input(InputEvent::MouseMotion { x, y: 300., time: start + Duration::from_millis(100 * (i as u64 + 1)) });
+        }
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Middle, x: 370., y: 300. });
+        state.process_messages();
+
+        assert_eq!(state.camera, Vec2::new(230., 200.));
+        assert!((state.camera_velocity - Vec2::new(100., 0.)).length() < 0.01);
+
+        state.apply_camera_momentum(0.1, state.viewport);
+        assert!((state.camera - Vec2::new(240., 200.)).length() < 0.01);
+        assert!((state.camera_velocity - Vec2::new(60., 0.)).length() < 0.01);
+    }
+
+    #[test]
+    fn menu_text_uses_the_theme_menu_color() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.theme.menu_text_color = [0., 1., 0., 1.];
+
+        let (dest, param) = state.menu_text_param(100., 50., 2);
+
+        assert_eq!(param.color, Color::GREEN);
+        assert_ne!(param.color, Color::from(state.theme.text_color));
+        assert_eq!(dest, Vec2::new(110., 100.));
+    }
+
+    #[test]
+    fn display_options_come_from_the_config() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        state.apply_config(&WindowConfig {
+            dim_behind_modals: false,
+            emit_frame_ticks: true,
+            ack_topics: vec![Topic::SPRITE],
+            show_turn_timer: false,
+            minimap_recenter_on_click: false,
+            movables_alpha: 0.5,
+            max_log_lines: 2,
+            log_overflow: LogOverflow::SCROLL,
+            ..Default::default()
+        });
+
+        assert!(!state.dim_behind_modals);
+        assert!(state.emit_frame_ticks);
+        assert!(state.ack_topics.contains(&Topic::SPRITE));
+        assert!(!state.show_turn_timer);
+        assert!(!state.minimap.recenter_on_click);
+        assert_eq!(state.movables_alpha, 0.5);
+        assert_eq!(state.max_log_lines, 2);
+        assert_eq!(state.log_overflow, LogOverflow::SCROLL);
+    }
+
+    #[test]
+    fn screen_to_tile_follows_the_camera_and_the_zoom() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.tile_size = 32;
+        state.set_sprites((0..16).map(|i| Sprite::new(1, i % 4, i / 4, Layer::BACKGROUND)).collect());
+
+        assert_eq!(state.screen_to_tile(40., 10.), Some((1, 0)));
+
+        state.camera = Vec2::new(32., 32.);
+        assert_eq!(state.screen_to_tile(40., 10.), Some((2, 1)));
+
+        state.camera = Vec2::ZERO;
+        state.zoom = 2.;
+        assert_eq!(state.screen_to_tile(40., 10.), Some((0, 0)));
+    }
+
+    #[test]
+    fn tile_to_screen_is_the_inverse_of_screen_to_tile() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.tile_size = 32;
+        state.set_sprites((0..16).map(|i| Sprite::new(1, i % 4, i / 4, Layer::BACKGROUND)).collect());
+        state.camera = Vec2::new(-20., 10.);
+        state.zoom = 1.5;
+
+        for tile in [(0, 0), (3, 1), (2, 3)] {
+            let corner = state.tile_to_screen(tile);
+            assert_eq!(corner, (Vec2::new(tile.0 as f32, tile.1 as f32) * 32. - state.camera) * 1.5);
+            let inside = corner + Vec2::splat(16. * 1.5);
+            assert_eq!(state.screen_to_tile(inside.x, inside.y), Some(tile));
+        }
+    }
+
+    #[test]
+    fn backdrop_dims_behind_open_modals_unless_disabled() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        assert!(!state.is_dimming_behind_modals());
+
+        state.push_modal(10., 10., "Goblin".to_string());
+        assert!(state.is_dimming_behind_modals());
+
+        state.apply_config(&WindowConfig { dim_behind_modals: false, ask_player_name: false, ..Default::default() });
+        assert!(!state.is_dimming_behind_modals());
+    }
+
+    #[test]
+    fn frame_ticks_carry_the_frame_number_once_enabled() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("frame_tick".to_string(), sender)]));
+
+        state.tick_frame(0.5);
+        assert!(receiver.try_recv().is_err());
+
+        state.set_emit_frame_ticks(true);
+        state.tick_frame(1.5);
+        let tick = receiver.try_recv().unwrap();
+        assert_eq!(bincode::deserialize::<(u64, f64)>(tick.content.as_slice()).unwrap(), (2, 1.5));
+    }
+
+    #[test]
+    fn outlines_stay_between_the_theme_bounds_on_screen() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        assert_eq!(state.outline_thickness(), 2.);
+
+        state.zoom = 3.;
+        assert_eq!(state.outline_thickness() * state.zoom, 4.);
+
+        state.zoom = 0.5;
+        assert_eq!(state.outline_thickness() * state.zoom, 1.);
+    }
+
+    #[test]
+    fn page_keys_scroll_the_log_between_the_oldest_and_newest_lines() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.stdout = (0..45).map(|i| format!("line {}", i)).collect::<Vec<String>>().join("\n");
+        let first_visible = |state: &MainState| state.visible_log()[0].1.clone();
+
+        state.handle_key_down(key(KeyCode::PageUp));
+        assert_eq!(first_visible(&state), "line 0");
+        assert!(!state.log_auto_follow);
+
+        state.handle_key_down(key(KeyCode::End));
+        assert_eq!(first_visible(&state), "line 15");
+        assert!(state.log_auto_follow);
+
+        state.handle_key_down(key(KeyCode::Home));
+        assert_eq!(first_visible(&state), "line 0");
+        state.handle_key_down(key(KeyCode::PageDown));
+        assert_eq!(state.log_scroll, 0);
+    }
+
+    #[test]
+    fn clicking_the_action_bar_sends_the_action_of_the_button() {
+        let (actions_sender, actions_receiver) = channel();
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("available_actions".to_string(), actions_receiver)]),
+                                                HashMap::from([("action".to_string(), sender)]));
+        actions_sender.send(MessageContent {
+            topic: "available_actions".to_string(),
+            content: bincode::serialize(&vec![Actions::MOVE.to_u8(), Actions::ATTACK.to_u8(), 99]).unwrap(),
+            request_id: None,
+        }).unwrap();
+        state.process_messages();
+        assert_eq!(state.available_actions, vec![Actions::MOVE, Actions::ATTACK]);
+
+        //The buttons are laid out when the bar is drawn
+        state.action_bar_buttons = vec![Rect::new(10., 500., 100., 24.), Rect::new(120., 500., 100., 24.)];
+        state.handle_mouse_button_up(MouseButton::Left, 150., 510.);
+
+        let action = receiver.try_recv().unwrap();
+        assert_eq!(bincode::deserialize::<u8>(action.content.as_slice()).unwrap(), Actions::ATTACK.to_u8());
+    }
+
+    #[test]
+    fn layer_alpha_scales_the_alpha_of_the_draws() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let param = DrawParam::new().color(Color::new(1., 1., 1., 0.8));
+
+        state.set_layer_alpha(Layer::UI, 0.5);
+        assert_eq!(MainState::with_layer_alpha(param, state.ui_alpha).color.a, 0.4);
+        assert_eq!(MainState::with_layer_alpha(param, state.movables_alpha).color.a, 0.8);
+
+        //The background is batched, a new alpha rebuilds the batches
+        state.background_dirty = false;
+        state.set_layer_alpha(Layer::BACKGROUND, 0.5);
+        assert!(state.background_dirty);
+    }
+
+    #[test]
+    fn queued_clicks_are_handled_after_the_sprites_of_the_frame() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("sprite".to_string(), receiver)]),
+                                                HashMap::from([("selection_changed".to_string(), channel().0)]));
+        let mut sprites = (0..9).map(|i| Sprite::new(1, i % 3, i / 3, Layer::BACKGROUND)).collect::<Vec<Sprite>>();
+        sprites.push(Sprite::new(201, 2, 0, Layer::MOVABLES));
+
+        //The goblin arrives on the clicked tile in the same frame as the click
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Left, x: 80., y: 16. });
+        sender.send(MessageContent { topic: "sprite".to_string(), content: encode_sprites(&sprites).unwrap(), request_id: None }).unwrap();
+        state.process_messages();
+
+        assert_eq!(state.selection, Some((2, 0)));
+        assert!(state.input_queue.is_empty());
+    }
+
+    #[test]
+    fn watchable_cells_are_overlaid_during_watch_only() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("watchable".to_string(), receiver)]),
+                                                HashMap::from([("selection_changed".to_string(), channel().0)]));
+        sender.send(MessageContent {
+            topic: "watchable".to_string(),
+            content: bincode::serialize(&vec![vec![false, true], vec![true, true]]).unwrap(),
+            request_id: None,
+        }).unwrap();
+
+        state.wait_for_watch();
+        assert_eq!(state.watchable_overlay.len(), 3);
+        assert!(state.watchable_overlay.iter().all(|(_, param)| param.color.a == WATCHABLE_OVERLAY_ALPHA));
+
+        state.clear_after_turn();
+        assert!(state.watchable_overlay.is_empty());
+    }
+
+    #[test]
+    fn dragging_on_the_minimap_pans_the_camera_by_the_matching_world_distance() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites((0..10000).map(|i| Sprite::new(1, i % 100, i / 100, Layer::BACKGROUND)).collect());
+        state.set_minimap_recenter_on_click(false);
+
+        //The 160x120 minimap sits in the top right corner of the 800x600 viewport and shows the 3200x3200 world
+        state.handle_mouse_button_down(MouseButton::Left, 650., 20., false);
+        state.handle_mouse_motion(660., 26., Instant::now());
+        assert_eq!(state.camera, Vec2::new(200., 160.));
+
+        state.handle_mouse_button_up(MouseButton::Left, 660., 26.);
+        state.handle_mouse_motion(700., 60., Instant::now());
+        assert_eq!(state.camera, Vec2::new(200., 160.));
+    }
+
+    #[test]
+    fn minimized_windows_drop_their_input_and_map_no_tile() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("selection_changed".to_string(), channel().0)]));
+        state.set_sprites(vec![Sprite::new(1, 0, 0, Layer::BACKGROUND), Sprite::new(201, 0, 0, Layer::MOVABLES)]);
+        state.viewport = (0., 0.);
+
+        assert_eq!(state.screen_to_tile(16., 16.), None);
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Left, x: 16., y: 16. });
+        state.process_messages();
+        assert!(state.input_queue.is_empty());
+        assert_eq!(state.selection, None);
+
+        state.viewport = (800., 600.);
+        assert_eq!(state.screen_to_tile(16., 16.), Some((0, 0)));
+    }
+
+    #[test]
+    fn turn_timer_counts_down_and_signals_the_timeout_once() {
+        let (timer_sender, timer_receiver) = channel();
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("turn_timer".to_string(), timer_receiver)]),
+                                                HashMap::from([("turn_timeout".to_string(), sender)]));
+        timer_sender.send(MessageContent { topic: "turn_timer".to_string(), content: bincode::serialize(&10f32).unwrap(), request_id: None }).unwrap();
+        state.process_messages();
+        assert_eq!(state.turn_timer, Some((10., 10.)));
+
+        state.update_turn_timer(4.);
+        assert_eq!(state.turn_timer, Some((6., 10.)));
+        assert!(receiver.try_recv().is_err());
+
+        state.update_turn_timer(6.);
+        state.update_turn_timer(1.);
+        assert_eq!(state.turn_timer, None);
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn low_life_creatures_pulse_unless_the_player_blink_is_disabled() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let healthy = Sprite::new(201, 1, 1, Layer::MOVABLES).with_life(9, 10);
+        let wounded = Sprite::new(201, 2, 1, Layer::MOVABLES).with_life(2, 10);
+        let player = Sprite::new(200, 3, 1, Layer::MOVABLES).with_life(2, 10).with_important(true);
+        let lowest_alpha = |state: &MainState, sprite: &Sprite| (0..100)
+            .map(|i| state.low_life_alpha(sprite, i as f32 / 100.))
+            .fold(1., f32::min);
+
+        assert_eq!(lowest_alpha(&state, &healthy), 1.);
+        assert!(lowest_alpha(&state, &wounded) < 0.35);
+        assert!(lowest_alpha(&state, &player) < 0.35);
+
+        state.set_blink_low_life_player(false);
+        assert_eq!(lowest_alpha(&state, &player), 1.);
+        assert!(lowest_alpha(&state, &wounded) < 0.35);
+    }
+
+    #[test]
+    fn quiet_logs_fade_to_their_minimum_until_hovered() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_log_fade(Duration::from_millis(100), 0.3);
+        state.last_log_message = Instant::now() - Duration::from_secs(1);
+        state.mouse.set_pointer_position(0., 0.);
+
+        state.update_log_alpha(0.5);
+        assert_eq!(state.log_alpha, 0.5);
+        state.update_log_alpha(0.5);
+        assert_eq!(state.log_alpha, 0.3);
+
+        state.mouse.set_pointer_position(300., 10.);
+        state.update_log_alpha(0.5);
+        assert_eq!(state.log_alpha, 1.);
+    }
+
+    #[test]
+    fn player_hits_highlight_the_source_tile_for_a_while() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("player_hit".to_string(), receiver)]), HashMap::new());
+        sender.send(MessageContent { topic: "player_hit".to_string(), content: bincode::serialize(&(4u16, 2u16)).unwrap(), request_id: None }).unwrap();
+
+        state.process_messages();
+        assert_eq!(state.damage_source.map(|(tile, _)| tile), Some((4, 2)));
+        assert!(state.damage_source_strength() > 0.9);
+
+        state.damage_source = Some(((4, 2), Instant::now() - DAMAGE_SOURCE_DURATION));
+        assert_eq!(state.damage_source_strength(), 0.);
+    }
+
+    #[test]
+    fn frozen_windows_read_their_messages_one_step_at_a_time() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("stdout".to_string(), receiver)]), HashMap::new());
+        let stdout = |text: &str| MessageContent { topic: "stdout".to_string(), content: text.as_bytes().to_vec(), request_id: None };
+        state.handle_key_down(key(KeyCode::F8));
+        sender.send(stdout("first")).unwrap();
+        sender.send(stdout("second")).unwrap();
+
+        state.process_messages();
+        assert!(!state.stdout.contains("first"));
+
+        state.handle_key_down(key(KeyCode::F9));
+        state.process_messages();
+        state.process_messages();
+        assert!(state.stdout.contains("first"));
+        assert!(!state.stdout.contains("second"));
+
+        state.handle_key_down(key(KeyCode::F8));
+        state.process_messages();
+        assert!(state.stdout.contains("second"));
+    }
+
+    #[test]
+    fn unanswered_watch_requests_show_a_fallback_instead_of_hanging() {
+        let (info_sender, _info_receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("info".to_string(), info_sender),
+                                                                               ("selection_changed".to_string(), channel().0)]));
+        state.set_sprites(vec![Sprite::new(1, 1, 1, Layer::BACKGROUND), Sprite::new(201, 1, 1, Layer::MOVABLES)]);
+        state.gameplay_state = Some(Actions::WATCH);
+
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Left, x: 48., y: 48. });
+        state.process_messages();
+        state.process_messages();
+        assert!(state.modals.is_empty());
+        assert_eq!(state.gameplay_state, Some(Actions::WATCH));
+
+        //The gameplay never answers
+        state.pending_info = Some(Instant::now() - Duration::from_secs(1));
+        state.process_messages();
+        assert_eq!(state.modals.last().map(|(_, _, content)| content.as_str()), Some("no info available"));
+        assert_eq!(state.gameplay_state, None);
+    }
+
+    #[test]
+    fn ui_sprites_get_their_own_draw_list() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        state.set_sprites(vec![
+            Sprite::new(10, 0, 0, Layer::BACKGROUND),
+            Sprite::new(201, 0, 0, Layer::MOVABLES),
+            Sprite::new(1, 2, 0, Layer::UI),
+        ]);
+
+        assert_eq!(state.sprites_ui.iter().map(|(texture_id, _)| *texture_id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(state.sprites_background.len(), 1);
+        assert_eq!(state.sprites_movables.len(), 1);
+    }
+
+    #[test]
+    fn sprites_are_placed_with_the_tile_size_of_the_window() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.tile_size = 64;
+
+        state.set_sprites((0..9).map(|i| Sprite::new(10, i % 3, i / 3, Layer::BACKGROUND)).collect());
+
+        assert_eq!(dest(&state.sprites_background[5].1), Vec2::new(128., 64.));
+        assert_eq!(state.screen_to_tile(130., 70.), Some((2, 1)));
+        assert_eq!(state.world_size(), Vec2::new(192., 192.));
+    }
+
+    #[test]
+    fn draw_lists_keep_the_texture_id_and_the_placement_of_each_sprite() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        state.set_sprites(vec![
+            Sprite::new(201, 1, 0, Layer::MOVABLES).with_tint([1., 0., 0., 1.]),
+            Sprite::new(200, 2, 1, Layer::MOVABLES),
+        ]);
+
+        let drawn = state.sprites_movables.iter()
+            .map(|(texture_id, param)| (*texture_id, dest(param), param.color))
+            .collect::<Vec<_>>();
+        assert_eq!(drawn, vec![(201, Vec2::new(32., 0.), Color::RED), (200, Vec2::new(64., 32.), Color::WHITE)]);
+        //No texture is loaded without a context, the draws of unknown ids are skipped
+        assert!(state.texture(201).is_none());
+    }
+
+    #[test]
+    fn log_keeps_its_newest_lines_and_wraps_the_long_ones() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_log_overflow(10. * LOG_CHAR_WIDTH, LogOverflow::WRAP);
+        state.set_max_log_lines(3);
+
+        for text in ["one", "two", "three", "a long line to wrap"] {
+            state.handle_stdout(MessageContent { topic: "stdout".to_string(), content: text.as_bytes().to_vec(), request_id: None });
+        }
+
+        assert_eq!(state.stdout.lines().count(), 3);
+        let displayed = state.display_log_lines().into_iter().map(|(_, line)| line).collect::<Vec<String>>();
+        assert_eq!(displayed, vec!["two", "three", "a long", "line to", "wrap"]);
+    }
+
+    #[test]
+    fn modals_close_on_escape_or_a_click_outside_and_release_the_watch() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("info".to_string(), sender)]));
+        state.watch_awaiting_release = true;
+        state.push_modal(10., 10., "Goblin".to_string());
+        state.push_modal(10., 10., "Troll".to_string());
+
+        state.handle_mouse_button_up(MouseButton::Left, 40., 40.);
+        assert_eq!(state.modals.len(), 2);
+
+        state.handle_key_down(key(KeyCode::Escape));
+        assert_eq!(state.modals.len(), 1);
+        assert!(receiver.try_recv().is_err());
+
+        state.handle_mouse_button_up(MouseButton::Left, 790., 590.);
+        assert!(state.modals.is_empty());
+        assert!(receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn menu_buttons_match_the_active_menu_on_every_frame() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.menu_to_show = vec![((0., 200.), vec!["Attack".to_string(), "Watch".to_string()]),
+                                  ((300., 200.), vec!["Goblin".to_string(), "Troll".to_string(), "Orc".to_string()])];
+
+        state.layout_menu_buttons();
+        state.layout_menu_buttons();
+
+        assert_eq!(state.menu_buttons.len(), 3);
+        assert!(state.menu_buttons.iter().all(|button| button.x > 300.));
+
+        state.menu_to_show.clear();
+        state.layout_menu_buttons();
+        assert!(state.menu_buttons.is_empty());
+    }
+
+    #[test]
+    fn right_clicks_ask_for_the_actions_of_the_tile_and_show_them_at_the_cursor() {
+        let (response_sender, response_receiver) = channel();
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("context_response".to_string(), response_receiver)]),
+                                                HashMap::from([("context".to_string(), sender), ("selection_changed".to_string(), channel().0)]));
+        state.set_sprites(vec![Sprite::new(10, 1, 1, Layer::BACKGROUND), Sprite::new(201, 1, 1, Layer::MOVABLES)]);
+
+        state.handle_mouse_button_up(MouseButton::Right, 40., 50.);
+        let request = receiver.try_recv().unwrap();
+        assert_eq!(bincode::deserialize::<(u16, u16)>(request.content.as_slice()).unwrap(), (1, 1));
+        assert_eq!(state.selection, None);
+
+        response_sender.send(MessageContent {
+            topic: "context_response".to_string(),
+            content: bincode::serialize(&vec![Actions::ATTACK.to_u8(), Actions::WATCH.to_u8()]).unwrap(),
+            request_id: None,
+        }).unwrap();
+        state.process_messages();
+        assert_eq!(state.menu_to_show, vec![((40., 50.), vec!["ATTACK".to_string(), "WATCH".to_string()])]);
+    }
+
+    #[test]
+    fn closing_the_window_tells_the_gameplay_even_when_it_is_gone() {
+        let (sender, receiver) = channel();
+        let state = MainState::new_headless(HashMap::new(), HashMap::from([("quit".to_string(), sender)]));
+
+        state.notify_quit();
+        assert_eq!(receiver.try_recv().unwrap().topic, "quit");
+
+        drop(receiver);
+        state.notify_quit();
+        assert_eq!(state.router.take_errors().len(), 1);
+    }
+
+    #[test]
+    fn sends_to_a_closed_gameplay_become_toasts() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("selection_changed".to_string(), sender)]));
+        drop(receiver);
+
+        state.set_selection(Some((1, 1)));
+        state.process_messages();
+
+        assert_eq!(state.selection, Some((1, 1)));
+        assert_eq!(state.toasts.len(), 1);
+        assert!(state.toasts[0].1.contains("selection_changed"));
+    }
+
+    #[test]
+    fn hover_highlight_snaps_to_the_tile_under_the_cursor() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites((0..9).map(|i| Sprite::new(10, i % 3, i / 3, Layer::BACKGROUND)).collect());
+        state.camera = Vec2::new(10., 0.);
+
+        assert_eq!(state.snap_to_tile(30., 40.), Some(Vec2::new(32., 32.)));
+        assert_eq!(state.snap_to_tile(100., 40.), None);
+
+        state.mouse.set_pointer_position(30., 40.);
+        state.update_hover();
+        assert_eq!(state.hover.map(|(tile, _)| tile), Some((1, 1)));
+    }
+
+    #[test]
+    fn window_is_created_with_the_configured_size_and_msaa() {
+        let config = WindowConfig { width: 1024., height: 768., samples: NumSamples::One, title: "dungeon".to_string(), ..Default::default() };
+
+        let (window_mode, window_setup) = window_conf(&config, context_attempts(&config)[0]);
+
+        assert_eq!((window_mode.width, window_mode.height), (1024., 768.));
+        assert_eq!(window_setup.samples, NumSamples::One);
+        assert_eq!(window_setup.title, "dungeon");
+    }
+
+    #[test]
+    fn malformed_payloads_keep_the_current_state_and_raise_toasts() {
+        let (sprite_sender, sprite_receiver) = channel();
+        let (stdout_sender, stdout_receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("sprite".to_string(), sprite_receiver), ("stdout".to_string(), stdout_receiver)]),
+                                                HashMap::new());
+        state.set_sprites(vec![Sprite::new(10, 0, 0, Layer::BACKGROUND)]);
+        state.stdout = "welcome".to_string();
+
+        sprite_sender.send(MessageContent { topic: "sprite".to_string(), content: vec![1, 2, 3], request_id: None }).unwrap();
+        stdout_sender.send(MessageContent { topic: "stdout".to_string(), content: vec![0xff, 0xfe], request_id: None }).unwrap();
+        state.process_messages();
+
+        assert_eq!(state.sprites.len(), 1);
+        assert_eq!(state.stdout, "welcome");
+        assert_eq!(state.toasts.len(), 2);
+    }
+
+    #[test]
+    fn each_topic_of_a_frame_reaches_its_handler() {
+        let topics = ["clear", "stdout", "select"];
+        let channels = topics.iter().map(|topic| (topic.to_string(), channel())).collect::<Vec<_>>();
+        let senders = channels.iter().map(|(topic, (sender, _))| (topic.clone(), sender.clone())).collect::<HashMap<_, _>>();
+        let mut state = MainState::new_headless(channels.into_iter().map(|(topic, (_, receiver))| (topic, receiver)).collect(), HashMap::new());
+        let send = |topic: &str, content: Vec<u8>| senders[topic].send(MessageContent { topic: topic.to_string(), content, request_id: None }).unwrap();
+        state.stdout = "old".to_string();
+        let options = vec!["Attack".to_string(), "Flee".to_string()];
+
+        send("clear", vec![]);
+        send("stdout", "new".as_bytes().to_vec());
+        send("select", bincode::serialize(&options).unwrap());
+        state.process_messages();
+
+        //The log is cleared before the new line is written
+        assert!(!state.stdout.contains("old"));
+        assert!(state.stdout.contains("new"));
+        assert_eq!(state.active_menu(), Some(&options));
+    }
+
+    #[test]
+    fn paused_windows_keep_their_messages_and_ignore_clicks_until_resumed() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("stdout".to_string(), receiver)]),
+                                                HashMap::from([("selection_changed".to_string(), channel().0)]));
+        state.set_sprites(vec![Sprite::new(10, 0, 0, Layer::BACKGROUND), Sprite::new(201, 0, 0, Layer::MOVABLES)]);
+
+        state.handle_key_down(key(KeyCode::P));
+        assert!(state.is_paused());
+        sender.send(MessageContent { topic: "stdout".to_string(), content: "hit".as_bytes().to_vec(), request_id: None }).unwrap();
+        state.queue_input(InputEvent::MouseUp { button: MouseButton::Left, x: 16., y: 16. });
+        state.process_messages();
+        assert!(!state.stdout.contains("hit"));
+        assert_eq!(state.selection, None);
+
+        state.handle_key_down(key(KeyCode::P));
+        state.process_messages();
+        assert!(state.stdout.contains("hit"));
+    }
+
+    #[test]
+    fn asset_root_given_at_startup_goes_in_the_config() {
+        assert_eq!(startup_config(None, None).resource_path, None);
+
+        let config = startup_config(Some("/opt/baston/assets"), None);
+
+        assert_eq!(config.resource_path, Some(PathBuf::from("/opt/baston/assets")));
+    }
+
+    #[test]
+    fn quick_second_clicks_do_not_answer_the_next_menu() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("select_response".to_string(), sender)]));
+        let menu = vec!["Attack".to_string(), "Flee".to_string()];
+        state.menu_to_show = vec![((0., 200.), menu.clone()), ((0., 200.), menu)];
+        let click = |state: &mut MainState| {
+            state.layout_menu_buttons();
+            let button = state.menu_buttons[0];
+            state.handle_mouse_button_up(MouseButton::Left, button.x + 1., button.y + 1.);
+        };
+
+        click(&mut state);
+        click(&mut state);
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(state.menu_to_show.len(), 1);
+
+        state.last_menu_click = Some(Instant::now() - Duration::from_secs(1));
+        click(&mut state);
+        assert!(receiver.try_recv().is_ok());
+        assert!(state.menu_to_show.is_empty());
+    }
+
+    #[test]
+    fn modals_grow_with_the_lines_of_their_content() {
+        let state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let minimum = state.modal_size("Goblin");
+
+        assert_eq!(minimum, Vec2::new(state.theme.modal_width, state.theme.modal_height));
+
+        let content = (0..20).map(|i| format!("Line {}", i)).chain(["x".repeat(40)]).collect::<Vec<String>>().join("\n");
+        assert_eq!(state.modal_size(content.as_str()), Vec2::new(40. * LOG_CHAR_WIDTH + 20., 21. * 20. + 20.));
+    }
+
+    #[test]
+    fn watch_info_is_only_requested_once_the_hover_settles() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("info".to_string(), sender)]));
+        state.set_sprites(vec![Sprite::new(10, 0, 0, Layer::BACKGROUND), Sprite::new(10, 1, 0, Layer::BACKGROUND)]);
+        state.gameplay_state = Some(Actions::WATCH);
+
+        state.mouse.set_pointer_position(10., 10.);
+        state.update_hover();
+        state.update_hover();
+        assert!(receiver.try_recv().is_err());
+
+        //Moving to another tile restarts the delay
+        state.hover = Some(((0, 0), Instant::now() - TOOLTIP_DELAY));
+        state.mouse.set_pointer_position(40., 10.);
+        state.update_hover();
+        assert!(receiver.try_recv().is_err());
+
+        state.hover = Some(((1, 0), Instant::now() - TOOLTIP_DELAY));
+        state.update_hover();
+        state.update_hover();
+        let request = receiver.try_recv().unwrap();
+        assert_eq!(bincode::deserialize::<(u16, u16)>(request.content.as_slice()).unwrap(), (1, 0));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn each_action_has_its_own_cursor_shape_and_color() {
+        let mut mouse = Mouse::default();
+        mouse.set_pointer_position(100., 50.);
+        let actions = [None, Some(Actions::ATTACK), Some(Actions::WATCH), Some(Actions::MOVE)];
+
+        let mut vertex_counts = vec![];
+        for action in &actions {
+            let shape = mouse.cursor_shape(16., action, Color::WHITE);
+            let data = shape.build();
+            let color = <[f32; 4]>::from(Mouse::cursor_color(action, Color::WHITE));
+            assert!(data.vertices.iter().all(|v| v.color == color));
+            //Strokes are 2 pixels wide, they may overflow the square by half of it
+            assert!(data.vertices.iter().all(|v| (99.0..=117.).contains(&v.position[0]) && (49.0..=67.).contains(&v.position[1])));
+            vertex_counts.push(data.vertices.len());
+        }
+        assert_eq!(Mouse::cursor_color(&Some(Actions::ATTACK), Color::WHITE), Color::RED);
+        vertex_counts.dedup();
+        assert_eq!(vertex_counts.len(), actions.len());
+    }
+
+    #[test]
+    fn middle_or_space_drags_pan_the_camera_against_the_motion() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites((0..10000).map(|i| Sprite::new(10, i % 100, i / 100, Layer::BACKGROUND)).collect());
+        state.camera = Vec2::new(500., 500.);
+        state.zoom = 2.;
+
+        state.handle_mouse_button_down(MouseButton::Middle, 300., 300., false);
+        state.handle_mouse_motion(260., 320., Instant::now());
+        assert_eq!(state.camera, Vec2::new(520., 490.));
+        state.handle_mouse_button_up(MouseButton::Middle, 260., 320.);
+
+        //A plain left press is a click, not a drag
+        state.handle_mouse_button_down(MouseButton::Left, 300., 300., false);
+        assert!(state.drag.is_none());
+        state.handle_mouse_button_down(MouseButton::Left, 300., 300., true);
+        assert!(state.drag.is_some());
+    }
+
+    #[test]
+    fn turn_banner_names_whose_turn_it_is() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let turn = |name: &str, playable: bool| MessageContent {
+            topic: "turn".to_string(),
+            content: bincode::serialize(&(name.to_string(), playable)).unwrap(),
+            request_id: None,
+        };
+        assert_eq!(state.turn_banner_text(), None);
+
+        state.handle_turn(turn("Goblin", false));
+        assert_eq!(state.turn_banner_text(), Some("Goblin's turn".to_string()));
+
+        state.handle_turn(turn("Warrior", true));
+        assert_eq!(state.turn_banner_text(), Some("Your turn".to_string()));
+
+        //An empty name ends the fight
+        state.handle_turn(turn("", false));
+        assert_eq!(state.turn_banner_text(), None);
+    }
+
+    #[test]
+    fn fps_overlay_shows_the_rate_and_the_last_frame_time() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.render_stats.frame_time = Duration::from_micros(16_700);
+
+        assert_eq!(state.fps_overlay_text(59.6), "60 fps 16.7 ms");
+        assert_eq!(state.render_stats().frame_time, Duration::from_micros(16_700));
+
+        state.handle_key_down(key(KeyCode::F3));
+        assert!(state.show_fps);
+        state.handle_key_down(key(KeyCode::F3));
+        assert!(!state.show_fps);
+    }
+
+    #[test]
+    fn clicks_left_of_the_board_are_not_rounded_onto_the_first_tile() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("move".to_string(), sender)]));
+        state.set_sprites((0..4).map(|i| Sprite::new(10, i % 2, i / 2, Layer::BACKGROUND)).collect());
+        state.camera = Vec2::new(-16., -16.);
+
+        //Half a pixel before the board is tile -1, not tile 0
+        assert_eq!(state.screen_to_tile(15.5, 20.), None);
+        assert_eq!(state.screen_to_tile(16.5, 20.), Some((0, 0)));
+
+        state.gameplay_state = Some(Actions::MOVE);
+        state.handle_mouse_button_up(MouseButton::Left, 15.5, 20.);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn ui_scale_multiplies_the_theme_text_scale() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.theme.text_scale = 1.2;
+
+        state.apply_config(&WindowConfig { ui_scale: 1.5, ask_player_name: false, ..Default::default() });
+        assert!((state.text_scale() - 1.8).abs() < 0.0001);
+        let scale = match state.menu_text_param(0., 0., 0).1.transform {
+            Transform::Values { scale, .. } => scale.x,
+            Transform::Matrix(_) => panic!("menu texts are placed by values"),
+        };
+        assert_eq!(scale, state.text_scale());
+
+        //Text never disappears
+        state.set_ui_scale(0.);
+        assert!(state.text_scale() > 0.);
+    }
+
+    #[test]
+    fn modals_stack_with_an_offset_and_the_oldest_goes_beyond_the_limit() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        for name in ["Goblin", "Troll", "Orc", "Dragon"] {
+            state.push_modal(100., 100., name.to_string());
+        }
+
+        let modals = state.modals.iter().map(|(x, y, content)| (*x, *y, content.as_str())).collect::<Vec<_>>();
+        assert_eq!(modals, vec![(116., 116., "Troll"), (132., 132., "Orc"), (132., 132., "Dragon")]);
+    }
+
+    #[test]
+    fn saved_window_size_is_used_at_startup() {
+        let config = startup_config(None, Some(WindowState { width: 1024., height: 768. }));
+
+        assert_eq!((config.width, config.height), (1024., 768.));
+        assert_eq!(startup_config(None, None).width, WindowConfig::default().width);
+    }
+
+    #[test]
+    fn clicks_select_creatures_and_clear_on_the_floor() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::from([("selection_changed".to_string(), sender)]));
+        state.set_sprites(vec![Sprite::new(10, 0, 0, Layer::BACKGROUND), Sprite::new(10, 1, 0, Layer::BACKGROUND), Sprite::new(201, 1, 0, Layer::MOVABLES)]);
+
+        state.handle_mouse_button_up(MouseButton::Left, 40., 10.);
+        assert_eq!(state.selection, Some((1, 0)));
+        assert_eq!(bincode::deserialize::<Option<(u16, u16)>>(receiver.try_recv().unwrap().content.as_slice()).unwrap(), Some((1, 0)));
+
+        state.handle_mouse_button_up(MouseButton::Left, 10., 10.);
+        assert_eq!(state.selection, None);
+
+        state.set_selection(Some((1, 0)));
+        state.handle_key_down(key(KeyCode::Escape));
+        assert_eq!(state.selection, None);
+    }
+
+    #[test]
+    fn toasts_expire_and_only_the_newest_are_kept() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        for i in 0..MAX_TOASTS + 2 {
+            state.push_toast(format!("error {}", i));
+        }
+        assert_eq!(state.toasts.len(), MAX_TOASTS);
+        assert_eq!(state.toasts[0].1, "error 2");
+
+        state.prune_toasts(Instant::now() + TOAST_DURATION);
+        assert!(state.toasts.is_empty());
+    }
+
+    #[test]
+    fn unloaded_texture_is_not_found_instead_of_panicking() {
+        let state = MainState::new_headless(HashMap::new(), HashMap::new());
+
+        assert!(state.texture(200).is_none());
+    }
+
+    #[test]
+    fn hit_test_finds_every_sprite_on_the_tile_under_the_pixel() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.set_sprites(vec![
+            Sprite::new(10, 1, 0, Layer::BACKGROUND),
+            Sprite::new(200, 1, 0, Layer::MOVABLES),
+            Sprite::new(10, 0, 0, Layer::BACKGROUND),
+        ]);
+
+        let hit = state.sprites_at_pixel(40., 10.).iter().map(|s| s.texture_id).collect::<Vec<u16>>();
+        assert_eq!(hit, vec![10, 200]);
+        assert!(state.sprites_at_pixel(100., 100.).is_empty());
+    }
+
+    #[test]
+    fn void_is_filled_with_the_theme_color() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        assert_eq!(state.void_color(), Color::BLACK);
+
+        state.theme.void_color = [0.1, 0.2, 0.3, 1.];
+        assert_eq!(state.void_color(), Color::new(0.1, 0.2, 0.3, 1.));
+    }
+
+    #[test]
+    fn queued_menus_are_shown_newest_first() {
+        let (sender, receiver) = channel();
+        let mut state = MainState::new_headless(HashMap::from([("menu".to_string(), receiver)]),
+                                                HashMap::from([("select_response".to_string(), channel().0)]));
+        let inventory = vec!["Potion".to_string(), "Sword".to_string()];
+        let confirm = vec!["Yes".to_string(), "No".to_string()];
+        for options in [&inventory, &confirm] {
+            sender.send(MessageContent {
+                topic: "menu".to_string(),
+                content: bincode::serialize(&((10., 20.), options)).unwrap(),
+                request_id: None,
+            }).unwrap();
+        }
+        //One menu is read per frame
+        state.process_messages();
+        state.process_messages();
+        assert_eq!(state.active_menu(), Some(&confirm));
+
+        state.send_menu_selection(0);
+        assert_eq!(state.active_menu(), Some(&inventory));
+    }
+
+    #[test]
+    fn interpolation_is_halfway_at_half_progress() {
+        let from = Vec2::new(0., 32.);
+        let to = Vec2::new(64., 0.);
+
+        assert_eq!(MainState::interpolate_position(from, to, 0.5), Vec2::new(32., 16.));
+        assert_eq!(MainState::interpolate_position(from, to, 0.), from);
+        assert_eq!(MainState::interpolate_position(from, to, 2.), to);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        assert_eq!(MainState::clamp_zoom(0.1), MIN_ZOOM);
+        assert_eq!(MainState::clamp_zoom(10.), MAX_ZOOM);
+        assert_eq!(MainState::clamp_zoom(1.5), 1.5);
+    }
+
+    #[test]
+    fn zooming_keeps_the_world_point_under_the_cursor() {
+        let camera = Vec2::new(100., 50.);
+        let cursor = Vec2::new(300., 200.);
+        let world_before = cursor + camera;
+
+        let new_camera = MainState::zoom_anchored_camera(camera, cursor, 1., 2.);
+
+        assert!((cursor / 2. + new_camera - world_before).length() < 0.001);
+    }
+
+    #[test]
+    fn menus_near_the_edges_open_inside_the_window() {
+        let size = Vec2::new(200., 100.);
+
+        assert_eq!(MainState::clamp_menu_position((50., 60.), size, (800., 600.)), (50., 60.));
+        assert_eq!(MainState::clamp_menu_position((750., 580.), size, (800., 600.)), (600., 500.));
+        assert_eq!(MainState::clamp_menu_position((50., 60.), size, (100., 50.)), (0., 0.));
+    }
+
+    #[test]
+    fn info_answers_to_an_older_request_are_ignored() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        state.info_request_id = 3;
+        let answer = |request_id| MessageContent {
+            topic: "info".to_string(),
+            content: vec![],
+            request_id,
+        };
+
+        assert!(!state.is_current_info_response(&answer(Some(2))));
+        assert!(!state.is_current_info_response(&answer(None)));
+        assert!(state.is_current_info_response(&answer(Some(3))));
+    }
+
+    #[test]
+    fn select_options_keep_their_colons() {
+        let mut state = MainState::new_headless(HashMap::new(), HashMap::new());
+        let options = vec!["Attack: goblin".to_string(), "Talk: merchant: 2 gold".to_string()];
+
+        state.handle_select(MessageContent {
+            topic: "select".to_string(),
+            content: bincode::serialize(&options).unwrap(),
+            request_id: None,
+        });
+
+        assert_eq!(state.active_menu(), Some(&options));
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+pub const WINDOW_STATE_PATH: &str = "window.json";
+
+//Saved when the window closes so the next session opens with the same size
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WindowState {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl WindowState {
+    //A missing, corrupt or degenerate file is ignored, the default size is used instead
+    pub fn load(path: &str) -> Option<WindowState> {
+        let content = fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<WindowState>(content.as_str()) {
+            Ok(state) if state.width > 0. && state.height > 0. => Some(state),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Cannot parse window state {} : {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) {
+        match serde_json::to_string_pretty(self) {
+            Ok(content) => {
+                if let Err(e) = fs::write(path, content) {
+                    log::warn!("Cannot save window state to {} : {}", path, e);
+                }
+            }
+            Err(e) => log::warn!("Cannot serialize window state : {}", e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::WindowState;
+
+    #[test]
+    fn saved_sizes_load_back_and_degenerate_ones_are_ignored() {
+        let path = std::env::temp_dir().join("baston_window_state.json");
+        let path = path.to_str().unwrap();
+
+        WindowState { width: 1024., height: 768. }.save(path);
+        assert_eq!(WindowState::load(path), Some(WindowState { width: 1024., height: 768. }));
+
+        WindowState { width: 0., height: 768. }.save(path);
+        assert_eq!(WindowState::load(path), None);
+
+        fs::write(path, "not json").unwrap();
+        assert_eq!(WindowState::load(path), None);
+        fs::remove_file(path).unwrap();
+        assert_eq!(WindowState::load(path), None);
+    }
+}
@@ -42,11 +42,12 @@ impl Menu {
         }
         #[cfg(feature = "graphical_mode")]
         {
-            let vec = options.join(":");
-            println!("vec : {}", vec);
+            //Sent as a list so labels can contain any character
+            log::debug!("options : {:?}", options);
             self.select_menu.send(MessageContent {
                 topic: "select".to_string(),
-                content: vec.as_bytes().to_vec(),
+                content: bincode::serialize(&options).unwrap(),
+                request_id: None,
             }).unwrap();
 
 
@@ -72,6 +73,7 @@ impl Menu {
             self.stdout.send(MessageContent {
                 topic: stdout_topic.to_string(),
                 content: out.as_bytes().to_vec(),
+                request_id: None,
             }).unwrap();
         }
         Ok(())
@@ -86,6 +88,7 @@ impl Menu {
             self.clear.send(MessageContent {
                 topic: "clear".to_string(),
                 content: Vec::new(),
+                request_id: None,
             }).unwrap();
         }
 
@@ -19,25 +19,46 @@ use crate::gui::menu::Menu;
 use crate::services::a_star::calculate_range;
 use crate::services::messaging::MessageContent;
 
+//Discriminants go over the wire between the gameplay and the window, never change existing ones
 #[warn(non_camel_case_types)]
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[repr(u8)]
 pub enum Actions {
     OPEN = 0,
-    ATTACK,
-    WALK_TO,
-    WATCH,
-    USE,
-    EQUIP,
+    ATTACK = 1,
+    WALK_TO = 2,
+    WATCH = 3,
+    USE = 4,
+    EQUIP = 5,
+    MOVE = 6,
 }
 
 impl Actions {
+    pub fn to_u8(&self) -> u8 {
+        self.clone() as u8
+    }
+
+    pub fn from_u8(value: u8) -> Option<Actions> {
+        match value {
+            0 => Some(Actions::OPEN),
+            1 => Some(Actions::ATTACK),
+            2 => Some(Actions::WALK_TO),
+            3 => Some(Actions::WATCH),
+            4 => Some(Actions::USE),
+            5 => Some(Actions::EQUIP),
+            6 => Some(Actions::MOVE),
+            _ => None
+        }
+    }
+
     pub fn vec_string() -> Vec<String> {
         vec!["Open".to_string(),
              "Attack".to_string(),
              "Walk to".to_string(),
              "Watch".to_string(),
              "Use".to_string(),
-             "Equip".to_string()]
+             "Equip".to_string(),
+             "Move".to_string()]
     }
 }
 
@@ -50,6 +71,7 @@ impl From<usize> for Actions {
             x if x == Actions::WALK_TO as usize => Actions::WALK_TO,
             x if x == Actions::WATCH as usize => Actions::WATCH,
             x if x == Actions::EQUIP as usize => Actions::EQUIP,
+            x if x == Actions::MOVE as usize => Actions::MOVE,
             _ => Actions::OPEN,
         }
     }
@@ -93,22 +115,23 @@ impl Actions {
             if let Some(action) = actions {
                 match action.into() {
                     Actions::USE => {
-                        println!("USE");
+                        log::debug!("USE");
                         Ok(())
                     }
                     Actions::WATCH => Self::watch_action(current_pawn.clone(), pawns, world, receivers, senders, menu, graphical_mode),
                     Actions::WALK_TO => {
-                        println!("WALK TO");
+                        log::debug!("WALK TO");
 
                         Ok(())
                     }
                     Actions::ATTACK => Self::attack_action(pawns, current_pawn.clone(), senders, receivers, menu, &world.places.get(0).unwrap().room, graphical_mode),
                     Actions::OPEN => {
-                        println!("OPEN");
+                        log::debug!("OPEN");
 
                         Ok(())
                     }
-                    Actions::EQUIP => Self::equip_item(current_pawn.clone(), menu)
+                    Actions::EQUIP => Self::equip_item(current_pawn.clone(), menu),
+                    Actions::MOVE => Self::move_action(current_pawn.clone(), pawns, receivers, senders, menu, &world.places.get(0).unwrap().room, graphical_mode),
                 }?;
             }
         }
@@ -127,14 +150,15 @@ impl Actions {
         if current_player.clone().borrow().playable {
             senders.get("gameplay_state").unwrap().send(MessageContent {
                 topic: "gameplay_state".to_string(),
-                content: bincode::serialize(&Actions::WATCH).unwrap(),
+                content: bincode::serialize(&Actions::WATCH.to_u8()).unwrap(),
+                request_id: None,
             }).unwrap();
 
             loop {
                 if let Ok(command) = receivers.get("info").unwrap().try_recv() {
                     let (x, y): (u16, u16) = bincode::deserialize(command.content.as_slice()).unwrap();
 
-                    println!("position {}, {}", x, y);
+                    log::debug!("position {}, {}", x, y);
                     let creatures = creatures.iter()
                         .filter(|c| c.borrow().position.y == y && c.borrow().position.x == x)
                         .map(|el| el.clone())
@@ -147,6 +171,7 @@ impl Actions {
                         senders.get("info_response").unwrap().send(MessageContent {
                             topic: "info_response".to_string(),
                             content: creature_stats.as_str().as_bytes().to_vec(),
+                            request_id: command.request_id,
                         }).unwrap();
                     } else {
                         let place: &Place = world.places.get(0).unwrap();
@@ -165,6 +190,7 @@ impl Actions {
                         senders.get("info_response").unwrap().send(MessageContent {
                             topic: "info_response".to_string(),
                             content: tile_info.as_bytes().to_vec(),
+                            request_id: command.request_id,
                         }).unwrap();
                     }
                     loop {
@@ -249,6 +275,52 @@ impl Actions {
         Ok(())
     }
 
+    //The window sends the clicked tile on the move topic, tiles outside of the room or taken by a living creature are refused
+    fn move_action(current_player: Rc<RefCell<Pawn>>,
+                   creatures: &Vec<Rc<RefCell<Pawn>>>,
+                   receivers: &HashMap<String, Receiver<MessageContent>>,
+                   senders: &HashMap<String, Sender<MessageContent>>,
+                   menu: &Menu,
+                   room: &Vec<Vec<u8>>,
+                   graphical_mode: bool) -> std::io::Result<()> {
+        if !graphical_mode || !current_player.clone().borrow().playable {
+            log::debug!("MOVE");
+            return Ok(());
+        }
+
+        loop {
+            //Sent again after a refused tile, the window leaves the move state once it has sent a tile
+            senders.get("gameplay_state").unwrap().send(MessageContent {
+                topic: "gameplay_state".to_string(),
+                content: bincode::serialize(&Actions::MOVE.to_u8()).unwrap(),
+                request_id: None,
+            }).unwrap();
+
+            //The window is gone, nobody will pick a tile anymore
+            let command = match receivers.get("move").unwrap().recv() {
+                Ok(command) => command,
+                Err(_) => return Ok(())
+            };
+            let (x, y): (u16, u16) = match bincode::deserialize(command.content.as_slice()) {
+                Ok(tile) => tile,
+                Err(e) => {
+                    log::warn!("Cannot decode the move target : {}", e);
+                    continue;
+                }
+            };
+
+            let inside_room = room.get(y as usize).map_or(false, |row| (x as usize) < row.len());
+            let occupied = creatures.iter()
+                .any(|c| c.borrow().life > 0 && c.borrow().position.x == x && c.borrow().position.y == y);
+            if inside_room && !occupied {
+                current_player.borrow_mut().position = Position { x, y };
+                menu.write_line(format!("{} moves to {}, {}", current_player.borrow().name, x, y).as_str())?;
+                return Ok(());
+            }
+
+            menu.write_line("Cannot move there")?;
+        }
+    }
 
     fn equip_item(player: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
         let selected_item: Option<Rc<Item>> =
@@ -322,7 +394,8 @@ impl Actions {
                      graphical_mode: bool) -> std::io::Result<()> {
         senders.get("gameplay_state").unwrap().send(MessageContent {
             topic: "gameplay_state".to_string(),
-            content: bincode::serialize(&Actions::ATTACK).unwrap(),
+            content: bincode::serialize(&Actions::ATTACK.to_u8()).unwrap(),
+            request_id: None,
         }).unwrap();
 
         let range = Self::calculate_range(player.clone(), room, 1);
@@ -396,6 +469,7 @@ impl Actions {
             senders.get("info_response").unwrap().send(MessageContent {
                 topic: "info_response".to_string(),
                 content: "end_attack".as_bytes().to_vec(),
+                request_id: None,
             }).unwrap();
         }
 
@@ -502,12 +576,14 @@ impl Actions {
             senders.get("targetable").unwrap().send(MessageContent {
                 topic: "targetable".to_string(),
                 content: bincode::serialize(&range).unwrap(),
+                request_id: None,
             }).unwrap();
 
             let info_receiver = receivers.get("info").unwrap();
-            let selected_target: (u16, u16) = loop {
+            //The answer echoes the id of the request so the UI can drop stale ones
+            let (selected_target, request_id): ((u16, u16), Option<u64>) = loop {
                 if let Ok(info) = info_receiver.try_recv() {
-                    break bincode::deserialize(info.content.as_slice()).unwrap();
+                    break (bincode::deserialize(info.content.as_slice()).unwrap(), info.request_id);
                 }
             };
             let vec = creatures.iter()
@@ -529,6 +605,7 @@ impl Actions {
                 senders.get("info_response").unwrap().send(MessageContent {
                     topic: "info_response".to_string(),
                     content: bincode::serialize(&(position, damage_type)).unwrap(),
+                    request_id: request_id,
                 }).unwrap();
                 return Ok((creatures.get(0).unwrap().clone()));
             } else {
@@ -543,4 +620,20 @@ impl Actions {
         let range = calculate_range((x, y), range, room);
         range
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Actions;
+
+    #[test]
+    fn discriminants_round_trip_and_stay_stable() {
+        let actions = [Actions::OPEN, Actions::ATTACK, Actions::WALK_TO, Actions::WATCH, Actions::USE, Actions::EQUIP, Actions::MOVE];
+
+        for (discriminant, action) in actions.iter().enumerate() {
+            assert_eq!(action.to_u8(), discriminant as u8);
+            assert_eq!(Actions::from_u8(action.to_u8()).as_ref(), Some(action));
+        }
+        assert_eq!(Actions::from_u8(actions.len() as u8), None);
+    }
+}
@@ -6,7 +6,7 @@ use std::sync::Arc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use crate::environment::world::World;
-use crate::gui::graphical::sprite::{Layer, ObjectToSprite, Sprite};
+use crate::gui::graphical::sprite::{encode_sprites, Layer, ObjectToSprite, Sprite};
 use crate::gui::menu::Menu;
 use crate::interact::actions::Actions;
 use crate::pawn::pawn::{Characteristics, Pawn, Position};
@@ -27,6 +27,7 @@ impl GameLoop {
                 id: idgenerator::IdInstance::next_id(),
                 name: "Toto".to_string(),
                 life: 100,
+                max_life: 100,
                 spell: vec![spells.get(0).unwrap().clone()],
                 race: "human".to_string(),
                 inventory: vec![Rc::new(items.remove(0))],
@@ -69,7 +70,7 @@ impl GameLoop {
                 .enumerate()
                 .map(|(row, cols)| cols.iter()
                     .enumerate()
-                    .map(|(col, &el)| Sprite::new(el, col as i32, row as i32, Layer::BACKGROUND))
+                    .map(|(col, &el)| Sprite::new(el.into(), col as i32, row as i32, Layer::BACKGROUND))
                     .collect::<Vec<Sprite>>())
                 .flatten()
                 .collect::<Vec<Sprite>>();
@@ -85,7 +86,8 @@ impl GameLoop {
 
             let message_content = MessageContent {
                 topic: "sprite".to_string(),
-                content: bincode::serialize(&pawns_sprites).unwrap(),
+                content: encode_sprites(&pawns_sprites).unwrap(),
+                request_id: None,
             };
 
             let sender = senders.get("sprite").unwrap();
@@ -93,6 +95,11 @@ impl GameLoop {
 
 
             loop {
+                //The window was closed, no one is left to play against
+                if receivers.get("quit").map_or(false, |quit| quit.try_recv().is_ok()) {
+                    return Ok(());
+                }
+
                 let creatures = (&pawns)
                     .iter()
                     .filter(|e| !e.borrow().playable)
@@ -120,7 +127,8 @@ impl GameLoop {
 
                 let message_content = MessageContent {
                     topic: "sprite".to_string(),
-                    content: bincode::serialize(&sprites).unwrap(),
+                    content: encode_sprites(&sprites).unwrap(),
+                    request_id: None,
                 };
 
                 let sender = senders.get("sprite").unwrap();
@@ -20,6 +20,8 @@ mod ai;
 
 
 fn main() {
+    //Verbosity is set with RUST_LOG, e.g. RUST_LOG=debug
+    env_logger::init();
     let mut messaging = Messaging::init();
 
     let select = messaging.create_topic();
@@ -40,19 +42,43 @@ fn main() {
     messenger_gameplay_sender.insert("info_response".to_string(), messaging.create_topic());
     messenger_gameplay_sender.insert("gameplay_state".to_string(), messaging.create_topic());
     messenger_gameplay_receiver.insert("info".to_string(), messaging.subscribe_to_topic("info".to_string()));
+    messenger_gameplay_receiver.insert("move".to_string(), messaging.subscribe_to_topic("move".to_string()));
+    messenger_gameplay_receiver.insert("quit".to_string(), messaging.subscribe_to_topic("quit".to_string()));
 
     let mut messenger_ui_map_receiver = HashMap::new();
     let mut messenger_ui_map_sender = HashMap::new();
 
     messenger_ui_map_receiver.insert("sprite".to_string(), messaging.subscribe_to_topic("sprite".to_string()));
+    messenger_ui_map_receiver.insert("sprite_delta".to_string(), messaging.subscribe_to_topic("sprite_delta".to_string()));
     messenger_ui_map_receiver.insert("stdout".to_string(), messaging.subscribe_to_topic("stdout".to_string()));
     messenger_ui_map_receiver.insert("select".to_string(), messaging.subscribe_to_topic("select".to_string()));
+    messenger_ui_map_receiver.insert("menu".to_string(), messaging.subscribe_to_topic("menu".to_string()));
     messenger_ui_map_receiver.insert("targetable".to_string(), messaging.subscribe_to_topic("targetable".to_string()));
     messenger_ui_map_receiver.insert("clear".to_string(), messaging.subscribe_to_topic("clear".to_string()));
     messenger_ui_map_receiver.insert("info_response".to_string(), messaging.subscribe_to_topic("info_response".to_string()));
     messenger_ui_map_receiver.insert("gameplay_state".to_string(), messaging.subscribe_to_topic("gameplay_state".to_string()));
+    messenger_ui_map_receiver.insert("available_actions".to_string(), messaging.subscribe_to_topic("available_actions".to_string()));
+    messenger_ui_map_receiver.insert("weather".to_string(), messaging.subscribe_to_topic("weather".to_string()));
+    messenger_ui_map_receiver.insert("watchable".to_string(), messaging.subscribe_to_topic("watchable".to_string()));
+    messenger_ui_map_receiver.insert("combat_event".to_string(), messaging.subscribe_to_topic("combat_event".to_string()));
+    messenger_ui_map_receiver.insert("aoe_preview".to_string(), messaging.subscribe_to_topic("aoe_preview".to_string()));
+    messenger_ui_map_receiver.insert("turn_timer".to_string(), messaging.subscribe_to_topic("turn_timer".to_string()));
+    messenger_ui_map_receiver.insert("player_hit".to_string(), messaging.subscribe_to_topic("player_hit".to_string()));
+    messenger_ui_map_receiver.insert("context_response".to_string(), messaging.subscribe_to_topic("context_response".to_string()));
+    messenger_ui_map_receiver.insert("turn".to_string(), messaging.subscribe_to_topic("turn".to_string()));
+    messenger_ui_map_receiver.insert("ping".to_string(), messaging.subscribe_to_topic("ping".to_string()));
+    messenger_ui_map_receiver.insert("projectile".to_string(), messaging.subscribe_to_topic("projectile".to_string()));
     messenger_ui_map_sender.insert("select_response".to_string(), messaging.create_topic());
     messenger_ui_map_sender.insert("info".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("frame_tick".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("action".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("ack".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("turn_timeout".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("selection_changed".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("move".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("context".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("player_name".to_string(), messaging.create_topic());
+    messenger_ui_map_sender.insert("quit".to_string(), messaging.create_topic());
 
 
     Messaging::start_bus(messaging.incoming_messages, messaging.outcoming_messages).unwrap();
@@ -61,5 +87,5 @@ fn main() {
 
 
     // #[cfg(feature = "graphical_mode")]
-    window::init(messenger_ui_map_receiver, messenger_ui_map_sender).unwrap();
+    window::init(messenger_ui_map_receiver, messenger_ui_map_sender, None, window::DEFAULT_TILE_SIZE, None).unwrap();
 }
@@ -47,6 +47,7 @@ pub struct Pawn {
     pub id: i64,
     pub name: String,
     pub life: u8,
+    pub max_life: u8,
     pub mana: u8,
     pub characteristics: Characteristics,
     pub inventory: Vec<Rc<Item>>,
@@ -287,7 +288,10 @@ impl ObjectToSprite for Pawn {
             _ => 201
         };
 
-        vec![Sprite::new(texture_id, self.position.x as i32, self.position.y as i32, Layer::MOVABLES)]
+        vec![Sprite::new(texture_id, self.position.x as i32, self.position.y as i32, Layer::MOVABLES)
+            .with_important(self.playable)
+            .with_id(self.id)
+            .with_life(self.life.into(), self.max_life.into())]
     }
 }
 
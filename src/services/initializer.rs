@@ -133,6 +133,7 @@ impl Initializer {
                 id: idgenerator::IdInstance::next_id(),
                 name: "bad".to_string(),
                 life: 100,
+                max_life: 100,
                 mana: 0,
                 characteristics: Characteristics {
                     force: 5,
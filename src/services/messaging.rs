@@ -6,6 +6,8 @@ use std::thread;
 pub struct MessageContent {
     pub topic: String,
     pub content: Vec<u8>,
+    //Set on requests expecting an answer, the answer carries the same id
+    pub request_id: Option<u64>,
 }
 
 pub struct Messaging {
@@ -36,7 +38,7 @@ impl Messaging {
 
     pub fn start_bus(incoming_messages: Vec<Receiver<MessageContent>>, outcoming_messages: Vec<(String, Sender<MessageContent>)>) -> thread::Result<()> {
         let handle = thread::spawn(move || {
-            println!("Starting message bus loop");
+            log::debug!("Starting message bus loop");
 
             //We handle message while we do not get lifecycle message to close the bus
             loop {
@@ -51,9 +53,9 @@ impl Messaging {
                                 )
                                 .for_each(|(_, (topic,sender))|{
                                     match sender.send(message_content.clone()) {
-                                        Ok(()) => println!("Message on topic {} distibuted", topic),
+                                        Ok(()) => log::trace!("Message on topic {} distibuted", topic),
                                         Err(e) => {
-                                            println!("Error while distributing message on topic {} : {:#?}", topic, e.to_string());
+                                            log::warn!("Error while distributing message on topic {} : {:#?}", topic, e.to_string());
                                         }
                                     }
                                 })